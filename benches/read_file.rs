@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+//Times read_file on increasingly large graphs and fails if the time per node grows with the node
+//count, which would mean parsing is no longer linear.
+use rrtk_rsb::*;
+use std::hint::black_box;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+//How much slower per node the largest graph may be than the smallest before this fails. This is
+//loose to allow for cache effects and noise, but quadratic parsing is far past it.
+const MAX_SLOWDOWN: f64 = 3.0;
+const RUNS: u32 = 5;
+fn graph(node_count: u16) -> Vec<Node> {
+    (0..node_count)
+        .map(|i| {
+            let inputs = if i == 0 { vec![] } else { vec![i - 1, i / 2] };
+            Node::new(Ok(NodeType::Sum2), i as f64, 0.0, inputs)
+        })
+        .collect()
+}
+//The fastest of several runs, so that one slow run does not count
+fn time_per_node(file: &Vec<u8>, node_count: u16) -> Duration {
+    let iterations = (1_000_000 / node_count as u32).max(5);
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                black_box(read_file(black_box(file)).unwrap());
+            }
+            start.elapsed() / iterations / node_count as u32
+        })
+        .min()
+        .unwrap()
+}
+fn main() -> ExitCode {
+    let mut per_node = Vec::new();
+    for node_count in [1000, 4000, 16000, 64000] {
+        let file = build_file(graph(node_count).iter());
+        let time = time_per_node(&file, node_count);
+        println!(
+            "{:>6} nodes, {:>8} bytes: {:>8.1?} per node",
+            node_count,
+            file.len(),
+            time
+        );
+        per_node.push(time);
+    }
+    let slowdown = per_node.last().unwrap().as_secs_f64() / per_node[0].as_secs_f64();
+    println!(
+        "largest graph is {:.2}x slower per node than smallest",
+        slowdown
+    );
+    if slowdown > MAX_SLOWDOWN {
+        eprintln!("read_file is not linear in the node count");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,383 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+//A graph builder which checks the types of connections at compile time
+use super::*;
+use core::marker::PhantomData;
+//Marker types for handles to nodes whose outputs are not plain Rust types. They can't be
+//constructed.
+pub enum Quantity {}
+pub enum State {}
+pub enum Command {}
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+    impl Sealed for bool {}
+    impl Sealed for i64 {}
+    impl Sealed for super::Quantity {}
+    impl Sealed for super::State {}
+    impl Sealed for super::Command {}
+}
+pub trait StreamValue: sealed::Sealed {
+    const STREAM_TYPE: StreamType;
+}
+//Types which arithmetic nodes work on
+pub trait Number: StreamValue {}
+//Types which can be stored in a parameter
+pub trait Constant: StreamValue + Copy {
+    const VALUE_TYPE: ValueType;
+    fn to_parameter(self) -> Parameter;
+}
+macro_rules! stream_value {
+    ($type:ty, $stream_type:ident) => {
+        impl StreamValue for $type {
+            const STREAM_TYPE: StreamType = StreamType::$stream_type;
+        }
+    };
+}
+stream_value!(f32, F32);
+stream_value!(f64, F64);
+stream_value!(bool, Bool);
+stream_value!(i64, I64);
+stream_value!(Quantity, Quantity);
+stream_value!(State, State);
+stream_value!(Command, Command);
+impl Number for f32 {}
+impl Number for f64 {}
+impl Number for i64 {}
+impl Number for Quantity {}
+impl Constant for f32 {
+    const VALUE_TYPE: ValueType = ValueType::F32;
+    fn to_parameter(self) -> Parameter {
+        Parameter::Float(self as f64)
+    }
+}
+impl Constant for f64 {
+    const VALUE_TYPE: ValueType = ValueType::F64;
+    fn to_parameter(self) -> Parameter {
+        Parameter::Float(self)
+    }
+}
+impl Constant for bool {
+    const VALUE_TYPE: ValueType = ValueType::Bool;
+    fn to_parameter(self) -> Parameter {
+        Parameter::Bool(self)
+    }
+}
+impl Constant for i64 {
+    const VALUE_TYPE: ValueType = ValueType::I64;
+    fn to_parameter(self) -> Parameter {
+        Parameter::Integer(self)
+    }
+}
+//Ties handles to the builder they came from. The lifetime is invariant and unique to each call
+//of Builder::build, so a handle can't be passed to any other builder.
+type Brand<'id> = PhantomData<fn(&'id ()) -> &'id ()>;
+//A node in a Builder whose output is a T. The traits are implemented by hand since deriving them
+//would require T to implement them too.
+pub struct Handle<'id, T> {
+    index: u16,
+    marker: PhantomData<fn() -> T>,
+    brand: Brand<'id>,
+}
+impl<'id, T> Clone for Handle<'id, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<'id, T> Copy for Handle<'id, T> {}
+impl<'id, T> fmt::Debug for Handle<'id, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+impl<'id, T> PartialEq for Handle<'id, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+impl<'id, T> Eq for Handle<'id, T> {}
+impl<'id, T> core::hash::Hash for Handle<'id, T> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+impl<'id, T> Handle<'id, T> {
+    //The index of the node in the list returned by Builder::finish
+    pub fn index(self) -> u16 {
+        self.index
+    }
+}
+#[derive(Debug, PartialEq)]
+pub struct Builder<'id> {
+    nodes: Vec<Node>,
+    brand: Brand<'id>,
+}
+impl Builder<'_> {
+    ///Calls `f` with a new builder and returns what it returns. Handles can't leave `f` or be
+    ///used with any other builder, so every input refers to a node in the same graph.
+    ///```compile_fail
+    ///use rrtk_rsb::*;
+    ///Builder::build(|mut outer| {
+    ///    let input = outer.constant(true);
+    ///    Builder::build(|mut inner| {
+    ///        inner.not(input);
+    ///    });
+    ///});
+    ///```
+    pub fn build<R>(f: impl for<'id> FnOnce(Builder<'id>) -> R) -> R {
+        f(Builder {
+            nodes: Vec::new(),
+            brand: PhantomData,
+        })
+    }
+}
+impl<'id> Builder<'id> {
+    //Panics if there are already 65536 nodes since inputs could not refer to any more.
+    fn push<T>(&mut self, node_type: NodeType, inputs: Vec<u16>) -> Handle<'id, T> {
+        let index = u16::try_from(self.nodes.len()).expect("too many nodes for u16 inputs");
+        self.nodes.push(Node::new(Ok(node_type), 0.0, 0.0, inputs));
+        Handle {
+            index,
+            marker: PhantomData,
+            brand: PhantomData,
+        }
+    }
+    fn push_typed<T>(&mut self, parameters: NodeParameters, inputs: Vec<u16>) -> Handle<'id, T> {
+        let handle = self.push(parameters.node_type(), inputs);
+        self.nodes[handle.index as usize].parameters = Some(parameters.to_parameters());
+        handle
+    }
+    fn annotate<T>(&mut self, handle: Handle<'id, T>, value_type: ValueType) -> Handle<'id, T> {
+        self.nodes[handle.index as usize].value_type = Some(value_type);
+        handle
+    }
+    //Nodes are at (0, 0) unless moved with this.
+    pub fn set_position<T>(&mut self, handle: Handle<'id, T>, x: f64, y: f64) -> Handle<'id, T> {
+        let node = &mut self.nodes[handle.index as usize];
+        node.x = x;
+        node.y = y;
+        handle
+    }
+    pub fn constant<T: Constant>(&mut self, value: T) -> Handle<'id, T> {
+        let value = value.to_parameter();
+        let handle = self.push_typed(NodeParameters::ConstantGetter { value }, vec![]);
+        self.annotate(handle, T::VALUE_TYPE)
+    }
+    pub fn quantity(&mut self, value: f64, dimension: Dimension) -> Handle<'id, Quantity> {
+        let value = Parameter::Float(value);
+        let handle = self.push_typed(NodeParameters::ConstantGetter { value }, vec![]);
+        self.annotate(handle, ValueType::Quantity(dimension))
+    }
+    pub fn none_getter<T: Constant>(&mut self) -> Handle<'id, T> {
+        let handle = self.push(NodeType::NoneGetter, vec![]);
+        self.annotate(handle, T::VALUE_TYPE)
+    }
+    pub fn expirer<T: StreamValue>(
+        &mut self,
+        input: Handle<'id, T>,
+        max_time_to_old: i64,
+    ) -> Handle<'id, T> {
+        self.push_typed(
+            NodeParameters::Expirer { max_time_to_old },
+            vec![input.index],
+        )
+    }
+    //Panics if inputs is empty.
+    pub fn latest<T: StreamValue>(&mut self, inputs: &[Handle<'id, T>]) -> Handle<'id, T> {
+        assert!(!inputs.is_empty(), "Latest needs at least one input");
+        let inputs = inputs.iter().map(|input| input.index).collect();
+        self.push(NodeType::Latest, inputs)
+    }
+    pub fn command_pid(
+        &mut self,
+        input: Handle<'id, State>,
+        kp: f64,
+        ki: f64,
+        kd: f64,
+    ) -> Handle<'id, Command> {
+        self.push_typed(NodeParameters::CommandPID { kp, ki, kd }, vec![input.index])
+    }
+    pub fn ewma<T: Number>(
+        &mut self,
+        input: Handle<'id, T>,
+        smoothing_constant: f64,
+    ) -> Handle<'id, T> {
+        self.push_typed(
+            NodeParameters::EWMAStream { smoothing_constant },
+            vec![input.index],
+        )
+    }
+    pub fn moving_average<T: Number>(
+        &mut self,
+        input: Handle<'id, T>,
+        window: i64,
+    ) -> Handle<'id, T> {
+        self.push_typed(
+            NodeParameters::MovingAverageStream { window },
+            vec![input.index],
+        )
+    }
+    pub fn pid_controller(
+        &mut self,
+        input: Handle<'id, f32>,
+        setpoint: f64,
+        kp: f64,
+        ki: f64,
+        kd: f64,
+    ) -> Handle<'id, f32> {
+        self.push_typed(
+            NodeParameters::PIDControllerStream {
+                setpoint,
+                kp,
+                ki,
+                kd,
+            },
+            vec![input.index],
+        )
+    }
+    pub fn position_to_state(&mut self, input: Handle<'id, Quantity>) -> Handle<'id, State> {
+        self.push(NodeType::PositionToState, vec![input.index])
+    }
+    pub fn velocity_to_state(&mut self, input: Handle<'id, Quantity>) -> Handle<'id, State> {
+        self.push(NodeType::VelocityToState, vec![input.index])
+    }
+    pub fn acceleration_to_state(&mut self, input: Handle<'id, Quantity>) -> Handle<'id, State> {
+        self.push(NodeType::AccelerationToState, vec![input.index])
+    }
+    pub fn none_to_error<T: StreamValue>(&mut self, input: Handle<'id, T>) -> Handle<'id, T> {
+        self.push(NodeType::NoneToError, vec![input.index])
+    }
+    pub fn none_to_value<T: Constant>(
+        &mut self,
+        input: Handle<'id, T>,
+        value: T,
+    ) -> Handle<'id, T> {
+        let value = value.to_parameter();
+        self.push_typed(NodeParameters::NoneToValue { value }, vec![input.index])
+    }
+    pub fn float_to_quantity(
+        &mut self,
+        input: Handle<'id, f32>,
+        dimension: Dimension,
+    ) -> Handle<'id, Quantity> {
+        self.push_typed(
+            NodeParameters::FloatToQuantity { dimension },
+            vec![input.index],
+        )
+    }
+    pub fn quantity_to_float(&mut self, input: Handle<'id, Quantity>) -> Handle<'id, f32> {
+        self.push(NodeType::QuantityToFloat, vec![input.index])
+    }
+    pub fn dimension_adder(
+        &mut self,
+        input: Handle<'id, Quantity>,
+        dimension: Dimension,
+    ) -> Handle<'id, Quantity> {
+        self.push_typed(
+            NodeParameters::DimensionAdder { dimension },
+            vec![input.index],
+        )
+    }
+    pub fn dimension_remover(
+        &mut self,
+        input: Handle<'id, Quantity>,
+        dimension: Dimension,
+    ) -> Handle<'id, Quantity> {
+        self.push_typed(
+            NodeParameters::DimensionRemover { dimension },
+            vec![input.index],
+        )
+    }
+    pub fn freeze<T: StreamValue>(
+        &mut self,
+        input: Handle<'id, T>,
+        freezer: Handle<'id, bool>,
+    ) -> Handle<'id, T> {
+        self.push(NodeType::FreezeStream, vec![input.index, freezer.index])
+    }
+    pub fn if_stream<T: StreamValue>(
+        &mut self,
+        condition: Handle<'id, bool>,
+        input: Handle<'id, T>,
+    ) -> Handle<'id, T> {
+        self.push(NodeType::IfStream, vec![condition.index, input.index])
+    }
+    pub fn if_else<T: StreamValue>(
+        &mut self,
+        condition: Handle<'id, bool>,
+        if_true: Handle<'id, T>,
+        if_false: Handle<'id, T>,
+    ) -> Handle<'id, T> {
+        self.push(
+            NodeType::IfElseStream,
+            vec![condition.index, if_true.index, if_false.index],
+        )
+    }
+    pub fn and(&mut self, a: Handle<'id, bool>, b: Handle<'id, bool>) -> Handle<'id, bool> {
+        self.push(NodeType::AndStream, vec![a.index, b.index])
+    }
+    pub fn or(&mut self, a: Handle<'id, bool>, b: Handle<'id, bool>) -> Handle<'id, bool> {
+        self.push(NodeType::OrStream, vec![a.index, b.index])
+    }
+    pub fn not(&mut self, input: Handle<'id, bool>) -> Handle<'id, bool> {
+        self.push(NodeType::NotStream, vec![input.index])
+    }
+    //Panics if inputs is empty.
+    pub fn sum<T: Number>(&mut self, inputs: &[Handle<'id, T>]) -> Handle<'id, T> {
+        assert!(!inputs.is_empty(), "SumStream needs at least one input");
+        let inputs = inputs.iter().map(|input| input.index).collect();
+        self.push(NodeType::SumStream, inputs)
+    }
+    pub fn sum2<T: Number>(&mut self, a: Handle<'id, T>, b: Handle<'id, T>) -> Handle<'id, T> {
+        self.push(NodeType::Sum2, vec![a.index, b.index])
+    }
+    pub fn difference<T: Number>(
+        &mut self,
+        minuend: Handle<'id, T>,
+        subtrahend: Handle<'id, T>,
+    ) -> Handle<'id, T> {
+        self.push(
+            NodeType::DifferenceStream,
+            vec![minuend.index, subtrahend.index],
+        )
+    }
+    //Panics if inputs is empty.
+    pub fn product<T: Number>(&mut self, inputs: &[Handle<'id, T>]) -> Handle<'id, T> {
+        assert!(!inputs.is_empty(), "ProductStream needs at least one input");
+        let inputs = inputs.iter().map(|input| input.index).collect();
+        self.push(NodeType::ProductStream, inputs)
+    }
+    pub fn product2<T: Number>(&mut self, a: Handle<'id, T>, b: Handle<'id, T>) -> Handle<'id, T> {
+        self.push(NodeType::Product2, vec![a.index, b.index])
+    }
+    pub fn quotient<T: Number>(
+        &mut self,
+        dividend: Handle<'id, T>,
+        divisor: Handle<'id, T>,
+    ) -> Handle<'id, T> {
+        self.push(
+            NodeType::QuotientStream,
+            vec![dividend.index, divisor.index],
+        )
+    }
+    pub fn exponent(
+        &mut self,
+        base: Handle<'id, f32>,
+        exponent: Handle<'id, f32>,
+    ) -> Handle<'id, f32> {
+        self.push(NodeType::ExponentStream, vec![base.index, exponent.index])
+    }
+    pub fn derivative<T: Number>(&mut self, input: Handle<'id, T>) -> Handle<'id, T> {
+        self.push(NodeType::DerivativeStream, vec![input.index])
+    }
+    pub fn integral<T: Number>(&mut self, input: Handle<'id, T>) -> Handle<'id, T> {
+        self.push(NodeType::IntegralStream, vec![input.index])
+    }
+    //The nodes in the order they were added, ready for build_file
+    pub fn finish(self) -> Vec<Node> {
+        self.nodes
+    }
+}
@@ -0,0 +1,303 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+use super::*;
+use core::cmp::Reverse;
+use std::collections::BinaryHeap;
+impl NodeType {
+    //Nodes which only use earlier values of their inputs, so feedback through them is fine
+    pub fn is_delaying(self) -> bool {
+        matches!(
+            self,
+            Self::Latest
+                | Self::Expirer
+                | Self::FreezeStream
+                | Self::IntegralStream
+                | Self::DerivativeStream
+        )
+    }
+}
+fn is_delaying(node: &Node) -> bool {
+    node.id.is_ok_and(NodeType::is_delaying)
+}
+//Tarjan's algorithm over the nodes for which included returns true, following inputs. This is
+//written with an explicit stack since files can have up to 65536 nodes in a chain.
+fn components(nodes: &[Node], included: impl Fn(usize) -> bool) -> Vec<Vec<usize>> {
+    let mut index = vec![None; nodes.len()];
+    let mut lowlink = vec![0; nodes.len()];
+    let mut on_stack = vec![false; nodes.len()];
+    let mut stack = Vec::new();
+    let mut next = 0;
+    let mut output = Vec::new();
+    for root in 0..nodes.len() {
+        if !included(root) || index[root].is_some() {
+            continue;
+        }
+        //Each frame is a node and the position of the next input to look at.
+        let mut calls = vec![(root, 0)];
+        index[root] = Some(next);
+        lowlink[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some(frame) = calls.last_mut() {
+            let node = frame.0;
+            if let Some(input) = nodes[node].inputs.get(frame.1) {
+                frame.1 += 1;
+                let input = *input as usize;
+                if input >= nodes.len() || !included(input) {
+                    continue;
+                }
+                match index[input] {
+                    None => {
+                        index[input] = Some(next);
+                        lowlink[input] = next;
+                        next += 1;
+                        stack.push(input);
+                        on_stack[input] = true;
+                        calls.push((input, 0));
+                    }
+                    Some(input_index) if on_stack[input] => {
+                        lowlink[node] = lowlink[node].min(input_index);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some((parent, _)) = calls.last() {
+                lowlink[*parent] = lowlink[*parent].min(lowlink[node]);
+            }
+            if Some(lowlink[node]) == index[node] {
+                let mut component = Vec::new();
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                output.push(component);
+            }
+        }
+    }
+    output
+}
+//Every strongly connected component of the graph, each sorted by node index. Components come
+//before any component that uses them as an input.
+pub fn strongly_connected_components(nodes: &[Node]) -> Vec<Vec<usize>> {
+    components(nodes, |_| true)
+}
+fn is_loop(nodes: &[Node], component: &[usize]) -> bool {
+    component.len() > 1 || nodes[component[0]].inputs.contains(&(component[0] as u16))
+}
+//The shortest loop through the first node of a strongly connected component, in the order data
+//flows around it
+fn loop_path(nodes: &[Node], component: &[usize]) -> Vec<usize> {
+    let start = component[0];
+    //Indexed by position in component
+    let mut parents = vec![None; component.len()];
+    let mut queue = vec![start];
+    let mut position = 0;
+    let end = loop {
+        let node = queue[position];
+        position += 1;
+        if nodes[node].inputs.contains(&(start as u16)) {
+            break node;
+        }
+        for input in &nodes[node].inputs {
+            let input = *input as usize;
+            if let Ok(member) = component.binary_search(&input)
+                && input != start
+                && parents[member].is_none()
+            {
+                parents[member] = Some(node);
+                queue.push(input);
+            }
+        }
+    };
+    //This follows inputs back from end to start, which is the direction data flows.
+    let mut path = vec![start];
+    let mut node = end;
+    while node != start {
+        path.push(node);
+        node = parents[component.binary_search(&node).unwrap()].unwrap();
+    }
+    path
+}
+//Loops with no delaying node in them. Each one is a path of node indices where each node is an
+//input of the next and the last is an input of the first. One loop is reported for each group of
+//nodes which can all reach each other without going through a delaying node.
+pub fn algebraic_loops(nodes: &[Node]) -> Vec<Vec<usize>> {
+    let mut output = components(nodes, |node| !is_delaying(&nodes[node]))
+        .into_iter()
+        .filter(|component| is_loop(nodes, component))
+        .map(|component| loop_path(nodes, &component))
+        .collect::<Vec<_>>();
+    output.sort_unstable();
+    output
+}
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CycleError {
+    //A loop with no delaying node, as returned by algebraic_loops
+    pub path: Vec<usize>,
+}
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "nodes form a loop with no delaying node:")?;
+        for node in &self.path {
+            write!(f, " {} ->", node)?;
+        }
+        write!(f, " {}", self.path[0])
+    }
+}
+impl core::error::Error for CycleError {}
+//An order to evaluate the nodes in where every node comes after its inputs. The exception is an
+//input of a delaying node which depends on the delaying node itself, since that is feedback from
+//the previous update. Nodes which could go in either order keep their order from the list.
+//Inputs which refer to nodes that do not exist are ignored.
+pub fn evaluation_order(nodes: &[Node]) -> Result<Vec<usize>, CycleError> {
+    let mut component_of = vec![0; nodes.len()];
+    for (component_index, component) in strongly_connected_components(nodes).iter().enumerate() {
+        for node in component {
+            component_of[*node] = component_index;
+        }
+    }
+    let mut waiting_on = vec![0usize; nodes.len()];
+    let mut dependents = vec![Vec::new(); nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        for input in &node.inputs {
+            let input = *input as usize;
+            if input >= nodes.len()
+                || is_delaying(node) && component_of[input] == component_of[index]
+            {
+                continue;
+            }
+            waiting_on[index] += 1;
+            dependents[input].push(index);
+        }
+    }
+    let mut ready = (0..nodes.len())
+        .filter(|index| waiting_on[*index] == 0)
+        .map(Reverse)
+        .collect::<BinaryHeap<_>>();
+    let mut order = Vec::with_capacity(nodes.len());
+    while let Some(Reverse(index)) = ready.pop() {
+        order.push(index);
+        for dependent in &dependents[index] {
+            waiting_on[*dependent] -= 1;
+            if waiting_on[*dependent] == 0 {
+                ready.push(Reverse(*dependent));
+            }
+        }
+    }
+    if order.len() < nodes.len() {
+        let path = algebraic_loops(nodes).swap_remove(0);
+        return Err(CycleError { path });
+    }
+    Ok(order)
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReorderError {
+    WrongLength { expected: usize, found: usize },
+    IndexOutOfRange { position: usize, index: usize },
+    DuplicateIndex { position: usize, index: usize },
+    //Inputs are u16 indices, so nodes past this cannot be moved.
+    TooManyNodes { count: usize },
+}
+impl fmt::Display for ReorderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WrongLength { expected, found } => write!(
+                f,
+                "order has {} indices but there are {} nodes",
+                found, expected
+            ),
+            Self::IndexOutOfRange { position, index } => write!(
+                f,
+                "index {} at position {} of order is not a node",
+                index, position
+            ),
+            Self::DuplicateIndex { position, index } => write!(
+                f,
+                "index {} at position {} of order is already in order",
+                index, position
+            ),
+            Self::TooManyNodes { count } => {
+                write!(f, "{} nodes is too many to refer to with inputs", count)
+            }
+        }
+    }
+}
+impl core::error::Error for ReorderError {}
+//Puts the nodes in the given order and rewrites their inputs so that they still refer to the same
+//nodes. order must contain every index of nodes exactly once. Inputs which refer to nodes that do
+//not exist are left as they are.
+pub fn reorder(nodes: &[Node], order: &[usize]) -> Result<Vec<Node>, ReorderError> {
+    if nodes.len() > u16::MAX as usize + 1 {
+        return Err(ReorderError::TooManyNodes { count: nodes.len() });
+    }
+    if order.len() != nodes.len() {
+        return Err(ReorderError::WrongLength {
+            expected: nodes.len(),
+            found: order.len(),
+        });
+    }
+    let mut new_index = vec![None; nodes.len()];
+    for (position, index) in order.iter().enumerate() {
+        match new_index.get_mut(*index) {
+            None => {
+                return Err(ReorderError::IndexOutOfRange {
+                    position,
+                    index: *index,
+                });
+            }
+            Some(Some(_)) => {
+                return Err(ReorderError::DuplicateIndex {
+                    position,
+                    index: *index,
+                });
+            }
+            Some(new) => *new = Some(position as u16),
+        }
+    }
+    Ok(order
+        .iter()
+        .map(|index| {
+            let mut node = nodes[*index].clone();
+            for input in &mut node.inputs {
+                if let Some(Some(new)) = new_index.get(*input as usize) {
+                    *input = *new;
+                }
+            }
+            node
+        })
+        .collect())
+}
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SortError {
+    Cycle(CycleError),
+    Reorder(ReorderError),
+}
+impl fmt::Display for SortError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cycle(_) => write!(f, "nodes have no evaluation order"),
+            Self::Reorder(_) => write!(f, "failed to reorder nodes"),
+        }
+    }
+}
+impl core::error::Error for SortError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Cycle(error) => Some(error),
+            Self::Reorder(error) => Some(error),
+        }
+    }
+}
+//The nodes in evaluation order with their inputs rewritten to match
+pub fn sort_for_evaluation(nodes: &[Node]) -> Result<Vec<Node>, SortError> {
+    let order = evaluation_order(nodes).map_err(SortError::Cycle)?;
+    reorder(nodes, &order).map_err(SortError::Reorder)
+}
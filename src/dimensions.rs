@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+use super::*;
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DimensionMismatch {
+    pub node: usize,
+    pub input: usize,
+    pub expected: Dimension,
+    pub found: Dimension,
+}
+impl fmt::Display for DimensionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "input {} of node {} has dimension {} but should have {}",
+            self.input, self.node, self.found, self.expected
+        )
+    }
+}
+impl core::error::Error for DimensionMismatch {}
+//These are None if an exponent goes outside of i8.
+fn add(a: Dimension, b: Dimension) -> Option<Dimension> {
+    Some(Dimension::new(
+        a.length.checked_add(b.length)?,
+        a.time.checked_add(b.time)?,
+        a.mass.checked_add(b.mass)?,
+    ))
+}
+fn subtract(a: Dimension, b: Dimension) -> Option<Dimension> {
+    Some(Dimension::new(
+        a.length.checked_sub(b.length)?,
+        a.time.checked_sub(b.time)?,
+        a.mass.checked_sub(b.mass)?,
+    ))
+}
+const TIME: Dimension = Dimension::new(0, 1, 0);
+//Inputs which carry the same dimension as the node's output
+fn passed_through(node_type: NodeType, input: usize) -> bool {
+    match node_type {
+        NodeType::Expirer
+        | NodeType::Latest
+        | NodeType::EWMAStream
+        | NodeType::MovingAverageStream
+        | NodeType::NoneToError
+        | NodeType::NoneToValue
+        | NodeType::SumStream
+        | NodeType::Sum2
+        | NodeType::DifferenceStream => true,
+        NodeType::FreezeStream => input == 0,
+        NodeType::IfStream => input == 1,
+        NodeType::IfElseStream => input == 1 || input == 2,
+        _ => false,
+    }
+}
+//The dimension each of the state nodes expects its input to have
+fn expected_input(node_type: NodeType) -> Option<Dimension> {
+    match node_type {
+        NodeType::PositionToState => Some(Dimension::new(1, 0, 0)),
+        NodeType::VelocityToState => Some(Dimension::new(1, -1, 0)),
+        NodeType::AccelerationToState => Some(Dimension::new(1, -2, 0)),
+        _ => None,
+    }
+}
+fn parameter_dimension(node: &Node) -> Option<Dimension> {
+    match node.typed_parameters()? {
+        Ok(NodeParameters::FloatToQuantity { dimension })
+        | Ok(NodeParameters::DimensionAdder { dimension })
+        | Ok(NodeParameters::DimensionRemover { dimension }) => Some(dimension),
+        _ => None,
+    }
+}
+fn infer(node: &Node, dimensions: &[Option<Dimension>]) -> Option<Dimension> {
+    if let Some(ValueType::Quantity(dimension)) = node.value_type {
+        return Some(dimension);
+    }
+    let input = |index: usize| *dimensions.get(*node.inputs.get(index)? as usize)?;
+    let node_type = node.id.ok()?;
+    match node_type {
+        NodeType::FloatToQuantity => parameter_dimension(node),
+        NodeType::DimensionAdder => add(input(0)?, parameter_dimension(node)?),
+        NodeType::DimensionRemover => subtract(input(0)?, parameter_dimension(node)?),
+        NodeType::DerivativeStream => subtract(input(0)?, TIME),
+        NodeType::IntegralStream => add(input(0)?, TIME),
+        NodeType::ProductStream | NodeType::Product2 => (0..node.inputs.len())
+            .try_fold(Dimension::NONE, |product, index| {
+                add(product, input(index)?)
+            }),
+        NodeType::QuotientStream => subtract(input(0)?, input(1)?),
+        _ => (0..node.inputs.len())
+            .filter(|index| passed_through(node_type, *index))
+            .find_map(input),
+    }
+}
+//The dimension of each node's output. Nodes which do not output a Quantity or whose dimension
+//cannot be worked out are None.
+pub fn output_dimensions(nodes: &[Node]) -> Vec<Option<Dimension>> {
+    let mut dimensions = vec![None; nodes.len()];
+    loop {
+        let mut changed = false;
+        for (index, node) in nodes.iter().enumerate() {
+            if dimensions[index].is_none() {
+                dimensions[index] = infer(node, &dimensions);
+                changed |= dimensions[index].is_some();
+            }
+        }
+        if !changed {
+            return dimensions;
+        }
+    }
+}
+//Returns the dimension of each node's output, or every input whose dimension does not match.
+//Inputs of sums, differences and other nodes which pass their input through must all have the
+//same dimension, and inputs of the state nodes must be a position, velocity or acceleration.
+pub fn check_dimensions(nodes: &[Node]) -> Result<Vec<Option<Dimension>>, Vec<DimensionMismatch>> {
+    let dimensions = output_dimensions(nodes);
+    let mut mismatches = Vec::new();
+    for (index, node) in nodes.iter().enumerate() {
+        let Ok(node_type) = node.id else {
+            continue;
+        };
+        for (input, target) in node.inputs.iter().enumerate() {
+            let Some(Some(found)) = dimensions.get(*target as usize).copied() else {
+                continue;
+            };
+            let expected = if passed_through(node_type, input) {
+                dimensions[index]
+            } else {
+                expected_input(node_type)
+            };
+            if let Some(expected) = expected
+                && expected != found
+            {
+                mismatches.push(DimensionMismatch {
+                    node: index,
+                    input,
+                    expected,
+                    found,
+                });
+            }
+        }
+    }
+    if mismatches.is_empty() {
+        Ok(dimensions)
+    } else {
+        Err(mismatches)
+    }
+}
@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+use super::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+//Refers to one node of a Graph. A handle stays valid until its node is removed and is never
+//reused for another node after that. Handles only work with the graph they came from and its
+//clones.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NodeHandle {
+    graph: u64,
+    index: usize,
+    generation: u32,
+}
+impl fmt::Display for NodeHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+struct Entry {
+    //The inputs field of this is always empty. The real inputs are below.
+    node: Node,
+    inputs: Vec<NodeHandle>,
+}
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Slot {
+    generation: u32,
+    entry: Option<Entry>,
+}
+//Every graph gets its own id so that handles from other graphs can be told apart.
+fn next_graph_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+//A node graph which can be edited without keeping track of positional input indices
+#[derive(Clone, Debug)]
+pub struct Graph {
+    id: u64,
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    len: usize,
+}
+impl Default for Graph {
+    fn default() -> Self {
+        Self {
+            id: next_graph_id(),
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+        }
+    }
+}
+//Graphs with the same nodes are equal even though their handles are not interchangeable.
+impl PartialEq for Graph {
+    fn eq(&self, other: &Self) -> bool {
+        self.slots == other.slots && self.free == other.free && self.len == other.len
+    }
+}
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn handle(&self, index: usize, generation: u32) -> NodeHandle {
+        NodeHandle {
+            graph: self.id,
+            index,
+            generation,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    //The handles of every node in the order to_nodes puts them in
+    pub fn handles(&self) -> impl Iterator<Item = NodeHandle> + '_ {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.entry.as_ref()?;
+            Some(self.handle(index, slot.generation))
+        })
+    }
+    fn entry(&self, handle: NodeHandle) -> Result<&Entry, error::Graph> {
+        if handle.graph != self.id {
+            return Err(error::Graph::NodeNotFound(handle));
+        }
+        match self.slots.get(handle.index) {
+            Some(Slot {
+                generation,
+                entry: Some(entry),
+            }) if *generation == handle.generation => Ok(entry),
+            _ => Err(error::Graph::NodeNotFound(handle)),
+        }
+    }
+    fn entry_mut(&mut self, handle: NodeHandle) -> Result<&mut Entry, error::Graph> {
+        if handle.graph != self.id {
+            return Err(error::Graph::NodeNotFound(handle));
+        }
+        match self.slots.get_mut(handle.index) {
+            Some(Slot {
+                generation,
+                entry: Some(entry),
+            }) if *generation == handle.generation => Ok(entry),
+            _ => Err(error::Graph::NodeNotFound(handle)),
+        }
+    }
+    pub fn contains(&self, handle: NodeHandle) -> bool {
+        self.entry(handle).is_ok()
+    }
+    //The inputs field of the returned node is always empty. Use inputs for those.
+    pub fn node(&self, handle: NodeHandle) -> Result<&Node, error::Graph> {
+        Ok(&self.entry(handle)?.node)
+    }
+    //Changes to the inputs field of the node are ignored.
+    pub fn node_mut(&mut self, handle: NodeHandle) -> Result<&mut Node, error::Graph> {
+        Ok(&mut self.entry_mut(handle)?.node)
+    }
+    pub fn inputs(&self, handle: NodeHandle) -> Result<&[NodeHandle], error::Graph> {
+        Ok(&self.entry(handle)?.inputs)
+    }
+    //The inputs field of node is ignored. Use connect to give the node inputs.
+    pub fn add_node(&mut self, mut node: Node) -> NodeHandle {
+        node.inputs.clear();
+        let entry = Some(Entry {
+            node,
+            inputs: Vec::new(),
+        });
+        self.len += 1;
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index].entry = entry;
+                index
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    entry,
+                });
+                self.slots.len() - 1
+            }
+        };
+        self.handle(index, self.slots[index].generation)
+    }
+    //Every (node, input) pair where input number input of node refers to handle. A node which
+    //refers to itself is included.
+    pub fn dependents(&self, handle: NodeHandle) -> Vec<(NodeHandle, usize)> {
+        self.handles()
+            .flat_map(|node| {
+                self.slots[node.index]
+                    .entry
+                    .iter()
+                    .flat_map(|entry| entry.inputs.iter().enumerate())
+                    .filter(|(_, input)| **input == handle)
+                    .map(move |(input, _)| (node, input))
+            })
+            .collect()
+    }
+    //Removes the node and returns it without inputs. Inputs are positional, so removing a node
+    //which other nodes still refer to would change what they mean. Disconnect or replace those
+    //inputs first. Inputs of the node referring to itself are fine.
+    pub fn remove_node(&mut self, handle: NodeHandle) -> Result<Node, error::Graph> {
+        self.entry(handle)?;
+        if let Some((dependent, input)) = self
+            .dependents(handle)
+            .into_iter()
+            .find(|(dependent, _)| *dependent != handle)
+        {
+            return Err(error::Graph::NodeInUse {
+                node: handle,
+                dependent,
+                input,
+            });
+        }
+        let slot = &mut self.slots[handle.index];
+        let entry = slot.entry.take().unwrap();
+        //A slot whose generation would overflow is never used again so that old handles can't
+        //refer to a new node.
+        if let Some(generation) = slot.generation.checked_add(1) {
+            slot.generation = generation;
+            self.free.push(handle.index);
+        }
+        self.len -= 1;
+        Ok(entry.node)
+    }
+    //Adds from as the last input of to.
+    pub fn connect(&mut self, from: NodeHandle, to: NodeHandle) -> Result<(), error::Graph> {
+        self.entry(from)?;
+        self.entry_mut(to)?.inputs.push(from);
+        Ok(())
+    }
+    //Removes input number input of node and returns the node it referred to. Later inputs move
+    //down by one.
+    pub fn disconnect(
+        &mut self,
+        node: NodeHandle,
+        input: usize,
+    ) -> Result<NodeHandle, error::Graph> {
+        let inputs = &mut self.entry_mut(node)?.inputs;
+        if input >= inputs.len() {
+            return Err(error::Graph::InputNotFound { node, input });
+        }
+        Ok(inputs.remove(input))
+    }
+    //Makes input number input of node refer to from instead and returns the node it referred to
+    //before.
+    pub fn replace_input(
+        &mut self,
+        node: NodeHandle,
+        input: usize,
+        from: NodeHandle,
+    ) -> Result<NodeHandle, error::Graph> {
+        self.entry(from)?;
+        let inputs = &mut self.entry_mut(node)?.inputs;
+        match inputs.get_mut(input) {
+            Some(old) => Ok(core::mem::replace(old, from)),
+            None => Err(error::Graph::InputNotFound { node, input }),
+        }
+    }
+    //Node i of nodes gets the ith handle returned by handles.
+    pub fn from_nodes(nodes: Vec<Node>) -> Result<Self, error::Graph> {
+        let count = nodes.len();
+        for (node, inputs) in nodes.iter().enumerate() {
+            for (input, target) in inputs.inputs.iter().enumerate() {
+                if *target as usize >= count {
+                    return Err(error::Graph::InputOutOfRange {
+                        node,
+                        input,
+                        target: *target,
+                    });
+                }
+            }
+        }
+        let id = next_graph_id();
+        let slots = nodes
+            .into_iter()
+            .map(|mut node| {
+                let inputs = node
+                    .inputs
+                    .drain(..)
+                    .map(|input| NodeHandle {
+                        graph: id,
+                        index: input as usize,
+                        generation: 0,
+                    })
+                    .collect();
+                Slot {
+                    generation: 0,
+                    entry: Some(Entry { node, inputs }),
+                }
+            })
+            .collect();
+        Ok(Self {
+            id,
+            slots,
+            free: Vec::new(),
+            len: count,
+        })
+    }
+    //Nodes come in the same order as handles, with inputs as indices into the returned list.
+    pub fn to_nodes(&self) -> Result<Vec<Node>, error::Graph> {
+        if self.len > u16::MAX as usize + 1 {
+            return Err(error::Graph::TooManyNodes { count: self.len });
+        }
+        //Position of each slot's node in the output
+        let mut positions = vec![0u16; self.slots.len()];
+        for (position, handle) in self.handles().enumerate() {
+            positions[handle.index] = position as u16;
+        }
+        Ok(self
+            .slots
+            .iter()
+            .filter_map(|slot| slot.entry.as_ref())
+            .map(|entry| {
+                let mut node = entry.node.clone();
+                node.inputs = entry
+                    .inputs
+                    .iter()
+                    .map(|input| positions[input.index])
+                    .collect();
+                node
+            })
+            .collect())
+    }
+}
@@ -1,10 +1,64 @@
 // SPDX-License-Identifier: BSD-3-Clause
 // Copyright 2025 UxuginPython
+use core::fmt;
 use core::mem::transmute;
 pub const MAJOR: u8 = 1;
-pub const MINOR: u8 = 0;
+pub const MINOR: u8 = 1;
 pub const PATCH: u8 = 0;
 pub const PRE: u8 = 0;
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u8,
+    pub pre: u8,
+}
+impl Version {
+    pub const CURRENT: Self = Self::new(MAJOR, MINOR, PATCH, PRE);
+    pub const fn new(major: u8, minor: u8, patch: u8, pre: u8) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            pre,
+        }
+    }
+    ///Whether a reader for this specification version can read a file with the given version.
+    ///Files with the same major version are always compatible. Newer minor, patch, and pre
+    ///versions only add tags, which older readers skip.
+    pub fn is_compatible_with(self, file: Version) -> bool {
+        self.major == file.major
+    }
+}
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.major, self.minor, self.patch, self.pre
+        )
+    }
+}
+///Which file versions `read_file_with_policy` accepts.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum VersionPolicy {
+    ///Only accept files with the same major version as `Version::CURRENT` and no newer than it.
+    ///This is what `read_file` uses.
+    #[default]
+    Strict,
+    ///Also accept newer files that `Version::CURRENT` is compatible with, ignoring any tags added
+    ///since.
+    Compatible,
+}
+impl VersionPolicy {
+    pub fn accepts(self, file: Version) -> bool {
+        Version::CURRENT.is_compatible_with(file)
+            && match self {
+                Self::Strict => file <= Version::CURRENT,
+                Self::Compatible => true,
+            }
+    }
+}
 pub mod tags {
     pub const SKIP_1: i8 = -128;
     pub const SKIP_2: i8 = -127;
@@ -21,24 +75,38 @@ pub mod tags {
     pub const NODE_END: i8 = -2;
     pub const NODE_INPUT_LIST_START: i8 = 4;
     pub const NODE_INPUT_LIST_END: i8 = -4;
+    pub const PARAMETER_LIST_START: i8 = 5;
+    pub const PARAMETER_LIST_END: i8 = -5;
+    pub const PARAMETER_FLOAT: i8 = 6;
+    pub const PARAMETER_INTEGER: i8 = 7;
+    pub const PARAMETER_BOOL: i8 = 8;
+    pub const PARAMETER_TIME: i8 = 9;
+    pub const VALUE_TYPE: i8 = 10;
 }
 mod tags_u8 {
     use super::*;
-    pub const SKIP_1: u8 = unsafe { transmute(tags::SKIP_1) };
-    pub const SKIP_2: u8 = unsafe { transmute(tags::SKIP_2) };
-    pub const SKIP_4: u8 = unsafe { transmute(tags::SKIP_4) };
-    pub const SKIP_8: u8 = unsafe { transmute(tags::SKIP_8) };
-    pub const SKIP_16: u8 = unsafe { transmute(tags::SKIP_16) };
-    pub const SKIP_U8: u8 = unsafe { transmute(tags::SKIP_U8) };
-    pub const SKIP_U16: u8 = unsafe { transmute(tags::SKIP_U16) };
-    pub const NODE_ID: u8 = unsafe { transmute(tags::NODE_ID) };
-    pub const COORDINATES: u8 = unsafe { transmute(tags::COORDINATES) };
-    pub const NODE_SECTION_START: u8 = unsafe { transmute(tags::NODE_SECTION_START) };
-    pub const NODE_SECTION_END: u8 = unsafe { transmute(tags::NODE_SECTION_END) };
-    pub const NODE_START: u8 = unsafe { transmute(tags::NODE_START) };
-    pub const NODE_END: u8 = unsafe { transmute(tags::NODE_END) };
-    pub const NODE_INPUT_LIST_START: u8 = unsafe { transmute(tags::NODE_INPUT_LIST_START) };
-    pub const NODE_INPUT_LIST_END: u8 = unsafe { transmute(tags::NODE_INPUT_LIST_END) };
+    pub const SKIP_1: u8 = tags::SKIP_1 as u8;
+    pub const SKIP_2: u8 = tags::SKIP_2 as u8;
+    pub const SKIP_4: u8 = tags::SKIP_4 as u8;
+    pub const SKIP_8: u8 = tags::SKIP_8 as u8;
+    pub const SKIP_16: u8 = tags::SKIP_16 as u8;
+    pub const SKIP_U8: u8 = tags::SKIP_U8 as u8;
+    pub const SKIP_U16: u8 = tags::SKIP_U16 as u8;
+    pub const NODE_ID: u8 = tags::NODE_ID as u8;
+    pub const COORDINATES: u8 = tags::COORDINATES as u8;
+    pub const NODE_SECTION_START: u8 = tags::NODE_SECTION_START as u8;
+    pub const NODE_SECTION_END: u8 = tags::NODE_SECTION_END as u8;
+    pub const NODE_START: u8 = tags::NODE_START as u8;
+    pub const NODE_END: u8 = tags::NODE_END as u8;
+    pub const NODE_INPUT_LIST_START: u8 = tags::NODE_INPUT_LIST_START as u8;
+    pub const NODE_INPUT_LIST_END: u8 = tags::NODE_INPUT_LIST_END as u8;
+    pub const PARAMETER_LIST_START: u8 = tags::PARAMETER_LIST_START as u8;
+    pub const PARAMETER_LIST_END: u8 = tags::PARAMETER_LIST_END as u8;
+    pub const PARAMETER_FLOAT: u8 = tags::PARAMETER_FLOAT as u8;
+    pub const PARAMETER_INTEGER: u8 = tags::PARAMETER_INTEGER as u8;
+    pub const PARAMETER_BOOL: u8 = tags::PARAMETER_BOOL as u8;
+    pub const PARAMETER_TIME: u8 = tags::PARAMETER_TIME as u8;
+    pub const VALUE_TYPE: u8 = tags::VALUE_TYPE as u8;
 }
 #[non_exhaustive]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -81,17 +149,54 @@ impl TryFrom<u16> for NodeType {
     type Error = u16;
     fn try_from(was: u16) -> Result<Self, u16> {
         if was <= 31 {
-            return Ok(unsafe { transmute(was) });
+            return Ok(unsafe { transmute::<u16, NodeType>(was) });
         }
         Err(was)
     }
 }
+impl fmt::Display for NodeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
 pub fn node_type_result_to_u16(was: Result<NodeType, u16>) -> u16 {
     match was {
         Ok(node_type) => node_type as u16,
         Err(error) => error,
     }
 }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Parameter {
+    Float(f64),
+    Integer(i64),
+    Bool(bool),
+    Time(i64), //nanoseconds
+}
+//The element type of the values a node outputs. RRTK streams are generic over this, so the node
+//type alone does not say what a node works on.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ValueType {
+    F32,
+    F64,
+    Bool,
+    I64,
+    Quantity(Dimension),
+}
+//A code byte followed by the dimension exponents for Quantity
+fn value_type_to_bytes(value_type: ValueType) -> Vec<u8> {
+    match value_type {
+        ValueType::F32 => vec![0],
+        ValueType::F64 => vec![1],
+        ValueType::Bool => vec![2],
+        ValueType::I64 => vec![3],
+        ValueType::Quantity(dimension) => vec![
+            4,
+            dimension.length as u8,
+            dimension.time as u8,
+            dimension.mass as u8,
+        ],
+    }
+}
 #[non_exhaustive]
 #[derive(Clone, Debug, PartialEq)]
 pub struct Node {
@@ -99,189 +204,323 @@ pub struct Node {
     pub x: f64,
     pub y: f64,
     pub inputs: Vec<u16>,
+    //None if the node has no parameter list at all, as in files from before version 1.1.0.0
+    pub parameters: Option<Vec<Parameter>>,
+    //None if the file does not say what type of values the node works on
+    pub value_type: Option<ValueType>,
 }
 impl Node {
     pub fn new(id: Result<NodeType, u16>, x: f64, y: f64, inputs: Vec<u16>) -> Self {
         Self {
-            id: id,
-            x: x,
-            y: y,
-            inputs: inputs,
+            id,
+            x,
+            y,
+            inputs,
+            parameters: None,
+            value_type: None,
         }
     }
+    pub fn with_parameters(mut self, parameters: Vec<Parameter>) -> Self {
+        self.parameters = Some(parameters);
+        self
+    }
+    pub fn with_value_type(mut self, value_type: ValueType) -> Self {
+        self.value_type = Some(value_type);
+        self
+    }
 }
 fn bytes_to_u16(it: &[u8]) -> u16 {
-    assert_eq!(it.len(), 2);
-    unsafe { transmute([it[0], it[1]]) }
+    u16::from_le_bytes(it.try_into().unwrap())
 }
 fn u16_to_bytes(it: u16) -> [u8; 2] {
-    unsafe { transmute(it) }
+    it.to_le_bytes()
 }
 fn bytes_to_f64(it: &[u8]) -> f64 {
-    assert_eq!(it.len(), 8);
-    unsafe { transmute([it[0], it[1], it[2], it[3], it[4], it[5], it[6], it[7]]) }
+    f64::from_le_bytes(it.try_into().unwrap())
 }
 fn f64_to_bytes(it: f64) -> [u8; 8] {
-    unsafe { transmute(it) }
+    it.to_le_bytes()
 }
-mod categorizer {
-    use super::*;
-    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    enum BigSkipState {
-        NotBigSkip,
-        NeedU8,
-        NeedU16Byte0,
-        NeedU16Byte1(u8), //byte 0 is stored
-    }
+fn bytes_to_i64(it: &[u8]) -> i64 {
+    i64::from_le_bytes(it.try_into().unwrap())
+}
+fn i64_to_bytes(it: i64) -> [u8; 8] {
+    it.to_le_bytes()
+}
+pub mod token;
+use token::*;
+mod graph;
+pub use graph::{Graph, NodeHandle};
+mod node_parameters;
+pub use node_parameters::{Dimension, NodeParameters};
+pub mod builder;
+mod macros;
+pub use builder::{Builder, Handle};
+mod cycles;
+pub use cycles::{
+    CycleError, ReorderError, SortError, algebraic_loops, evaluation_order, reorder,
+    sort_for_evaluation, strongly_connected_components,
+};
+mod dimensions;
+pub use dimensions::{DimensionMismatch, check_dimensions, output_dimensions};
+mod validate;
+pub use validate::{Arity, Diagnostic, DiagnosticKind, Severity, validate};
+pub mod sim;
+mod text;
+pub use text::{TextError, TextErrorKind, from_text, to_text};
+mod typecheck;
+pub use typecheck::{Expected, StreamType, TypeMismatch, output_types, typecheck};
+pub mod error {
+    use core::fmt;
     #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub enum CategorizedByte {
-        Tag(u8),
-        Number(u8), //This can be used for parts of numbers as well, such as 1 byte from a u16.
-        Skip,
-    }
-    #[derive(Clone, Debug)]
-    pub struct Categorizer {
-        skip_next: u32,
-        big_skip_state: BigSkipState,
+    pub enum ParseFile {
+        LayoutBroken,
+        MagicNumbers,
+        Version,
+        MultipleNodeSections {
+            offset: usize,
+        },
+        ParseNode {
+            node: usize,
+            offset: usize,
+            error: parse_file::ParseNode,
+        },
+        TruncatedSkip {
+            offset: usize,
+        },
+        UnterminatedNodeSection {
+            offset: usize,
+        },
+        UnterminatedNode {
+            node: usize,
+            offset: usize,
+        },
+        UnterminatedInputList {
+            node: usize,
+            offset: usize,
+        },
+        UnterminatedParameterList {
+            node: usize,
+            offset: usize,
+        },
     }
-    impl Categorizer {
-        pub fn new() -> Self {
-            Self {
-                skip_next: 0,
-                big_skip_state: BigSkipState::NotBigSkip,
-            }
-        }
-        pub fn feed(&mut self, byte: u8) -> CategorizedByte {
-            if self.skip_next >= 1 {
-                self.skip_next -= 1;
-                return CategorizedByte::Number(byte);
-            }
-            match self.big_skip_state {
-                BigSkipState::NotBigSkip => {}
-                BigSkipState::NeedU8 => {
-                    self.skip_next = byte as u32 + 1;
-                    self.big_skip_state = BigSkipState::NotBigSkip;
-                    return CategorizedByte::Skip;
-                }
-                BigSkipState::NeedU16Byte0 => {
-                    self.big_skip_state = BigSkipState::NeedU16Byte1(byte);
-                    return CategorizedByte::Skip;
-                }
-                BigSkipState::NeedU16Byte1(byte0) => {
-                    self.skip_next = bytes_to_u16(&[byte0, byte]) as u32 + 1;
-                    self.big_skip_state = BigSkipState::NotBigSkip;
-                    return CategorizedByte::Skip;
-                }
-            }
-            match byte {
-                tags_u8::SKIP_1 => {
-                    self.skip_next = 1;
-                    return CategorizedByte::Skip;
-                }
-                tags_u8::SKIP_2 => {
-                    self.skip_next = 2;
-                    return CategorizedByte::Skip;
-                }
-                tags_u8::SKIP_4 => {
-                    self.skip_next = 4;
-                    return CategorizedByte::Skip;
+    impl fmt::Display for ParseFile {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Self::LayoutBroken => write!(f, "file is too short to contain a header"),
+                Self::MagicNumbers => write!(f, "file does not start with RSB magic numbers"),
+                Self::Version => write!(f, "file specification version is not supported"),
+                Self::MultipleNodeSections { offset } => {
+                    write!(f, "second node section at byte {}", offset)
                 }
-                tags_u8::SKIP_8 => {
-                    self.skip_next = 8;
-                    return CategorizedByte::Skip;
+                Self::ParseNode { node, offset, .. } => {
+                    write!(f, "failed to parse node {} at byte {}", node, offset)
                 }
-                tags_u8::SKIP_16 => {
-                    self.skip_next = 16;
-                    return CategorizedByte::Skip;
+                Self::TruncatedSkip { offset } => {
+                    write!(
+                        f,
+                        "skip tag at byte {} runs past the end of the file",
+                        offset
+                    )
                 }
-                tags_u8::SKIP_U8 => {
-                    self.big_skip_state = BigSkipState::NeedU8;
-                    return CategorizedByte::Skip;
+                Self::UnterminatedNodeSection { offset } => {
+                    write!(f, "node section at byte {} is never closed", offset)
                 }
-                tags_u8::SKIP_U16 => {
-                    self.big_skip_state = BigSkipState::NeedU16Byte0;
-                    return CategorizedByte::Skip;
+                Self::UnterminatedNode { node, offset } => {
+                    write!(f, "node {} at byte {} is never closed", node, offset)
                 }
-                _ => {}
+                Self::UnterminatedInputList { node, offset } => write!(
+                    f,
+                    "input list of node {} at byte {} is never closed",
+                    node, offset
+                ),
+                Self::UnterminatedParameterList { node, offset } => write!(
+                    f,
+                    "parameter list of node {} at byte {} is never closed",
+                    node, offset
+                ),
             }
-            CategorizedByte::Tag(byte)
         }
     }
-}
-use categorizer::*;
-fn hunt_tag(data: &[u8], tag: u8) -> Option<&[u8]> {
-    let mut categorizer = Categorizer::new();
-    for i in 0..data.len() {
-        let byte = categorizer.feed(data[i]);
-        if byte == CategorizedByte::Tag(tag) {
-            return Some(&data[i..data.len()]);
-        }
-    }
-    return None;
-}
-fn hunt_tags(data: &[u8], start: u8, end: u8) -> Vec<&[u8]> {
-    let mut inside = 0u8;
-    let mut sections = Vec::<&[u8]>::new();
-    let mut current_section_start: Option<usize> = None;
-    let mut categorizer = Categorizer::new();
-    for i in 0..data.len() {
-        let byte = categorizer.feed(data[i]);
-        if byte == CategorizedByte::Tag(start) {
-            if inside == 0 {
-                current_section_start = Some(i + 1);
+    impl core::error::Error for ParseFile {
+        fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+            match self {
+                Self::ParseNode { error, .. } => Some(error),
+                _ => None,
             }
-            inside += 1;
         }
-        if byte == CategorizedByte::Tag(end) && inside > 0 {
-            inside -= 1;
-            if inside == 0 {
-                let real_current_section_start = current_section_start.unwrap();
-                if real_current_section_start < i {
-                    sections.push(&data[real_current_section_start..i]);
+    }
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub enum BuildFile {
+        TooManyNodes {
+            count: usize,
+        },
+        InputOutOfRange {
+            node: usize,
+            input: usize,
+            target: u16,
+        },
+        UnsupportedVersion(super::Version),
+        Downconvert {
+            version: super::Version,
+            unsupported: Vec<build_file::Unsupported>,
+        },
+    }
+    impl fmt::Display for BuildFile {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Self::TooManyNodes { count } => write!(
+                    f,
+                    "{} nodes cannot all be referred to by u16 input indices",
+                    count
+                ),
+                Self::InputOutOfRange {
+                    node,
+                    input,
+                    target,
+                } => write!(
+                    f,
+                    "input {} of node {} refers to node {}, which does not exist",
+                    input, node, target
+                ),
+                Self::UnsupportedVersion(version) => {
+                    write!(f, "cannot write files for version {}", version)
+                }
+                Self::Downconvert {
+                    version,
+                    unsupported,
+                } => {
+                    write!(f, "graph uses features not in version {}:", version)?;
+                    for unsupported in unsupported {
+                        write!(f, " {};", unsupported)?;
+                    }
+                    Ok(())
                 }
-                current_section_start = None;
             }
         }
     }
-    sections
-}
-fn hunt_numbers(data: &[u8], max_count: Option<usize>) -> Vec<u8> {
-    let mut output = Vec::new();
-    let mut categorizer = Categorizer::new();
-    for i in 0..data.len() {
-        let byte = categorizer.feed(data[i]);
-        match byte {
-            CategorizedByte::Number(x) => output.push(x),
-            _ => {}
-        }
-        if let Some(some_max_count) = max_count {
-            if output.len() >= some_max_count {
-                return output;
+    impl core::error::Error for BuildFile {}
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum Graph {
+        //The handle's node was removed or the handle is from another graph.
+        NodeNotFound(super::NodeHandle),
+        InputNotFound {
+            node: super::NodeHandle,
+            input: usize,
+        },
+        InputOutOfRange {
+            node: usize,
+            input: usize,
+            target: u16,
+        },
+        TooManyNodes {
+            count: usize,
+        },
+        //The node can't be removed since input number input of dependent refers to it.
+        NodeInUse {
+            node: super::NodeHandle,
+            dependent: super::NodeHandle,
+            input: usize,
+        },
+    }
+    impl fmt::Display for Graph {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Self::NodeNotFound(node) => write!(f, "node {} is not in the graph", node),
+                Self::InputNotFound { node, input } => {
+                    write!(f, "node {} has no input {}", node, input)
+                }
+                Self::InputOutOfRange {
+                    node,
+                    input,
+                    target,
+                } => write!(
+                    f,
+                    "input {} of node {} refers to node {}, which does not exist",
+                    input, node, target
+                ),
+                Self::TooManyNodes { count } => write!(
+                    f,
+                    "{} nodes cannot all be referred to by u16 input indices",
+                    count
+                ),
+                Self::NodeInUse {
+                    node,
+                    dependent,
+                    input,
+                } => write!(f, "node {} is input {} of node {}", node, input, dependent),
             }
         }
     }
-    output
-}
-pub mod error {
+    impl core::error::Error for Graph {}
     #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-    pub enum ParseFile {
-        LayoutBroken,
-        MagicNumbers,
-        Version,
-        MultipleNodeSections,
-        ParseNode(parse_file::ParseNode),
+    pub enum ParameterSchema {
+        Missing { index: usize },
+        Extra { index: usize },
+        WrongType { index: usize },
+        OutOfRange { index: usize },
     }
-    impl From<parse_file::ParseNode> for ParseFile {
-        fn from(was: parse_file::ParseNode) -> Self {
-            Self::ParseNode(was)
+    impl fmt::Display for ParameterSchema {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Self::Missing { index } => write!(f, "parameter {} is missing", index),
+                Self::Extra { index } => write!(f, "parameter {} is not expected", index),
+                Self::WrongType { index } => write!(f, "parameter {} has the wrong type", index),
+                Self::OutOfRange { index } => write!(f, "parameter {} is out of range", index),
+            }
+        }
+    }
+    impl core::error::Error for ParameterSchema {}
+    pub mod build_file {
+        use core::fmt;
+        #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+        pub struct Unsupported {
+            pub node: usize,
+            pub feature: super::super::Feature,
+        }
+        impl fmt::Display for Unsupported {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    f,
+                    "node {} uses {} from version {}",
+                    self.node,
+                    self.feature,
+                    self.feature.version()
+                )
+            }
         }
     }
     pub mod parse_file {
+        use core::fmt;
         #[derive(Clone, Copy, Debug, Eq, PartialEq)]
         pub enum ParseNode {
             ParseNodeID(parse_node::ParseNodeID),
             ParseCoordinates(parse_node::ParseCoordinates),
             ParseInputs(parse_node::ParseInputs),
+            ParseParameters(parse_node::ParseParameters),
+            ParseValueType(parse_node::ParseValueType),
+        }
+        impl fmt::Display for ParseNode {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    Self::ParseNodeID(_) => write!(f, "failed to parse node ID"),
+                    Self::ParseCoordinates(_) => write!(f, "failed to parse coordinates"),
+                    Self::ParseInputs(_) => write!(f, "failed to parse input list"),
+                    Self::ParseParameters(_) => write!(f, "failed to parse parameter list"),
+                    Self::ParseValueType(_) => write!(f, "failed to parse value type"),
+                }
+            }
+        }
+        impl core::error::Error for ParseNode {
+            fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+                match self {
+                    Self::ParseNodeID(error) => Some(error),
+                    Self::ParseCoordinates(error) => Some(error),
+                    Self::ParseInputs(error) => Some(error),
+                    Self::ParseParameters(error) => Some(error),
+                    Self::ParseValueType(error) => Some(error),
+                }
+            }
         }
         impl From<parse_node::ParseNodeID> for ParseNode {
             fn from(was: parse_node::ParseNodeID) -> Self {
@@ -298,239 +537,744 @@ pub mod error {
                 Self::ParseInputs(was)
             }
         }
+        impl From<parse_node::ParseParameters> for ParseNode {
+            fn from(was: parse_node::ParseParameters) -> Self {
+                Self::ParseParameters(was)
+            }
+        }
+        impl From<parse_node::ParseValueType> for ParseNode {
+            fn from(was: parse_node::ParseValueType) -> Self {
+                Self::ParseValueType(was)
+            }
+        }
         pub mod parse_node {
+            use core::fmt;
             #[derive(Clone, Copy, Debug, Eq, PartialEq)]
             pub enum ParseNodeID {
                 NotFound,
-                IncorrectLength,
+                IncorrectLength { offset: usize },
             }
             #[derive(Clone, Copy, Debug, Eq, PartialEq)]
             pub enum ParseCoordinates {
                 NotFound,
-                IncorrectLength,
+                IncorrectLength { offset: usize },
             }
             #[derive(Clone, Copy, Debug, Eq, PartialEq)]
             pub enum ParseInputs {
-                MultipleInputSections,
-                LayoutBroken,
+                MultipleInputSections { offset: usize },
+                LayoutBroken { offset: usize },
+            }
+            impl fmt::Display for ParseNodeID {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        Self::NotFound => write!(f, "node has no node ID tag"),
+                        Self::IncorrectLength { offset } => {
+                            write!(f, "node ID at byte {} is not followed by 2 bytes", offset)
+                        }
+                    }
+                }
+            }
+            impl core::error::Error for ParseNodeID {}
+            impl fmt::Display for ParseCoordinates {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        Self::NotFound => write!(f, "node has no coordinates tag"),
+                        Self::IncorrectLength { offset } => write!(
+                            f,
+                            "coordinates at byte {} are not followed by 16 bytes",
+                            offset
+                        ),
+                    }
+                }
             }
+            impl core::error::Error for ParseCoordinates {}
+            impl fmt::Display for ParseInputs {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        Self::MultipleInputSections { offset } => {
+                            write!(f, "second input list at byte {}", offset)
+                        }
+                        Self::LayoutBroken { offset } => write!(
+                            f,
+                            "input list at byte {} does not contain a whole number of inputs",
+                            offset
+                        ),
+                    }
+                }
+            }
+            impl core::error::Error for ParseInputs {}
+            #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+            pub enum ParseParameters {
+                MultipleParameterSections {
+                    offset: usize,
+                },
+                IncorrectLength {
+                    offset: usize,
+                },
+                InvalidBool {
+                    offset: usize,
+                },
+                Schema {
+                    offset: usize,
+                    error: super::super::ParameterSchema,
+                },
+            }
+            impl fmt::Display for ParseParameters {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        Self::MultipleParameterSections { offset } => {
+                            write!(f, "second parameter list at byte {}", offset)
+                        }
+                        Self::IncorrectLength { offset } => write!(
+                            f,
+                            "parameter at byte {} is not followed by the right number of bytes",
+                            offset
+                        ),
+                        Self::InvalidBool { offset } => {
+                            write!(f, "bool parameter at byte {} is not 0 or 1", offset)
+                        }
+                        Self::Schema { offset, .. } => write!(
+                            f,
+                            "parameter list at byte {} does not match its node type",
+                            offset
+                        ),
+                    }
+                }
+            }
+            impl core::error::Error for ParseParameters {
+                fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+                    match self {
+                        Self::Schema { error, .. } => Some(error),
+                        _ => None,
+                    }
+                }
+            }
+            #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+            pub enum ParseValueType {
+                IncorrectLength { offset: usize },
+                UnknownType { offset: usize, code: u8 },
+            }
+            impl fmt::Display for ParseValueType {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self {
+                        Self::IncorrectLength { offset } => write!(
+                            f,
+                            "value type at byte {} is not followed by the right number of bytes",
+                            offset
+                        ),
+                        Self::UnknownType { offset, code } => {
+                            write!(f, "value type at byte {} has unknown code {}", offset, code)
+                        }
+                    }
+                }
+            }
+            impl core::error::Error for ParseValueType {}
         }
     }
 }
-fn find_and_parse_node_id(data: &[u8]) -> Result<u16, error::parse_file::parse_node::ParseNodeID> {
-    let found = hunt_tag(data, tags_u8::NODE_ID);
-    let found = match found {
-        Some(x) => x,
-        None => return Err(error::parse_file::parse_node::ParseNodeID::NotFound),
-    };
-    let found_numbers = hunt_numbers(found, Some(2));
-    if found_numbers.len() != 2 {
-        return Err(error::parse_file::parse_node::ParseNodeID::IncorrectLength);
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum NodeField {
+    #[default]
+    None,
+    ID,
+    Coordinates,
+    ValueType,
+    Parameter(Tag, usize),
+}
+//Collects the contents of one node block as its tokens come in.
+#[derive(Clone, Debug, Default)]
+struct NodeBuilder {
+    field: NodeField,
+    id: Option<(usize, Vec<u8>)>,
+    coordinates: Option<(usize, Vec<u8>)>,
+    value_type: Option<(usize, Vec<u8>)>,
+    input_depth: u32,
+    input_list: (usize, Vec<u8>),
+    input_list_nonempty: bool,
+    input_lists: usize,
+    inputs: (usize, Vec<u8>),
+    second_input_list: usize,
+    parameter_depth: u32,
+    parameter_bytes: Vec<u8>,
+    parameter_list: (usize, Vec<Parameter>),
+    parameter_lists: usize,
+    parameters: Option<(usize, Vec<Parameter>)>,
+    parameter_error: Option<error::parse_file::parse_node::ParseParameters>,
+}
+impl NodeBuilder {
+    fn finish_parameter(&mut self) {
+        let NodeField::Parameter(tag, offset) = self.field else {
+            return;
+        };
+        self.field = NodeField::None;
+        let bytes = core::mem::take(&mut self.parameter_bytes);
+        let parameter = match (tag, bytes.len()) {
+            (Tag::ParameterFloat, 8) => Ok(Parameter::Float(bytes_to_f64(&bytes))),
+            (Tag::ParameterInteger, 8) => Ok(Parameter::Integer(bytes_to_i64(&bytes))),
+            (Tag::ParameterTime, 8) => Ok(Parameter::Time(bytes_to_i64(&bytes))),
+            (Tag::ParameterBool, 1) => match bytes[0] {
+                0 => Ok(Parameter::Bool(false)),
+                1 => Ok(Parameter::Bool(true)),
+                _ => Err(error::parse_file::parse_node::ParseParameters::InvalidBool { offset }),
+            },
+            _ => Err(error::parse_file::parse_node::ParseParameters::IncorrectLength { offset }),
+        };
+        match parameter {
+            Ok(parameter) => self.parameter_list.1.push(parameter),
+            Err(error) => {
+                self.parameter_error.get_or_insert(error);
+            }
+        }
+    }
+    fn feed(&mut self, kind: TokenKind, bytes: &[u8], offset: usize) {
+        if let TokenKind::Tag(_) = kind {
+            self.finish_parameter();
+        }
+        match kind {
+            TokenKind::Tag(Ok(Tag::NodeInputListEnd)) if self.input_depth == 1 => {
+                self.input_depth = 0;
+                if self.input_list_nonempty {
+                    self.input_lists += 1;
+                    match self.input_lists {
+                        1 => self.inputs = core::mem::take(&mut self.input_list),
+                        2 => self.second_input_list = self.input_list.0,
+                        _ => {}
+                    }
+                }
+                self.field = NodeField::None;
+                return;
+            }
+            _ if self.input_depth >= 1 => self.input_list_nonempty = true,
+            _ => {}
+        }
+        match kind {
+            TokenKind::Tag(Ok(Tag::NodeInputListStart)) => {
+                if self.input_depth == 0 {
+                    self.input_list = (offset, Vec::new());
+                    self.input_list_nonempty = false;
+                }
+                self.input_depth += 1;
+                self.field = NodeField::None;
+            }
+            TokenKind::Tag(Ok(Tag::NodeInputListEnd)) if self.input_depth >= 1 => {
+                self.input_depth -= 1;
+                self.field = NodeField::None;
+            }
+            TokenKind::Tag(Ok(Tag::NodeID)) => {
+                self.field = match self.id {
+                    None => {
+                        self.id = Some((offset, Vec::with_capacity(2)));
+                        NodeField::ID
+                    }
+                    Some(_) => NodeField::None,
+                };
+            }
+            TokenKind::Tag(Ok(Tag::Coordinates)) => {
+                self.field = match self.coordinates {
+                    None => {
+                        self.coordinates = Some((offset, Vec::with_capacity(16)));
+                        NodeField::Coordinates
+                    }
+                    Some(_) => NodeField::None,
+                };
+            }
+            TokenKind::Tag(Ok(Tag::ValueType)) => {
+                self.field = match self.value_type {
+                    None => {
+                        self.value_type = Some((offset, Vec::with_capacity(4)));
+                        NodeField::ValueType
+                    }
+                    Some(_) => NodeField::None,
+                };
+            }
+            TokenKind::Tag(Ok(Tag::ParameterListStart)) => {
+                if self.parameter_depth == 0 {
+                    self.parameter_list = (offset, Vec::new());
+                }
+                self.parameter_depth += 1;
+                self.field = NodeField::None;
+            }
+            TokenKind::Tag(Ok(Tag::ParameterListEnd)) if self.parameter_depth >= 1 => {
+                self.parameter_depth -= 1;
+                if self.parameter_depth == 0 {
+                    self.parameter_lists += 1;
+                    match self.parameter_lists {
+                        1 => self.parameters = Some(core::mem::take(&mut self.parameter_list)),
+                        2 => {
+                            use error::parse_file::parse_node::ParseParameters;
+                            self.parameter_error.get_or_insert(
+                                ParseParameters::MultipleParameterSections {
+                                    offset: self.parameter_list.0,
+                                },
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+                self.field = NodeField::None;
+            }
+            TokenKind::Tag(Ok(
+                tag @ (Tag::ParameterFloat
+                | Tag::ParameterInteger
+                | Tag::ParameterBool
+                | Tag::ParameterTime),
+            )) if self.parameter_depth >= 1 => self.field = NodeField::Parameter(tag, offset),
+            TokenKind::Tag(_) => self.field = NodeField::None,
+            TokenKind::Skip(_) => {}
+            TokenKind::Numbers => {
+                if self.input_depth >= 1 {
+                    self.input_list.1.extend_from_slice(bytes);
+                    return;
+                }
+                let (field, length) = match self.field {
+                    NodeField::None => return,
+                    //One extra byte is kept so that parameters which are too long are caught.
+                    NodeField::Parameter(..) => {
+                        let needed = (9 - self.parameter_bytes.len().min(9)).min(bytes.len());
+                        self.parameter_bytes.extend_from_slice(&bytes[..needed]);
+                        return;
+                    }
+                    NodeField::ID => (&mut self.id, 2),
+                    NodeField::Coordinates => (&mut self.coordinates, 16),
+                    //One extra byte is kept here too.
+                    NodeField::ValueType => (&mut self.value_type, 5),
+                };
+                let (_, field) = field.as_mut().unwrap();
+                let needed = (length - field.len()).min(bytes.len());
+                field.extend_from_slice(&bytes[..needed]);
+            }
+        }
+    }
+    fn finish(mut self) -> Result<Node, error::parse_file::ParseNode> {
+        self.finish_parameter();
+        let id = match self.id {
+            None => return Err(error::parse_file::parse_node::ParseNodeID::NotFound.into()),
+            Some((offset, id)) if id.len() != 2 => {
+                return Err(
+                    error::parse_file::parse_node::ParseNodeID::IncorrectLength { offset }.into(),
+                );
+            }
+            Some((_, id)) => bytes_to_u16(&id),
+        };
+        let (x, y) = match self.coordinates {
+            None => return Err(error::parse_file::parse_node::ParseCoordinates::NotFound.into()),
+            Some((offset, coordinates)) if coordinates.len() != 16 => {
+                return Err(
+                    error::parse_file::parse_node::ParseCoordinates::IncorrectLength { offset }
+                        .into(),
+                );
+            }
+            Some((_, coordinates)) => (
+                bytes_to_f64(&coordinates[0..=7]),
+                bytes_to_f64(&coordinates[8..=15]),
+            ),
+        };
+        if self.input_lists > 1 {
+            return Err(
+                error::parse_file::parse_node::ParseInputs::MultipleInputSections {
+                    offset: self.second_input_list,
+                }
+                .into(),
+            );
+        }
+        let (inputs_offset, inputs) = self.inputs;
+        if !inputs.len().is_multiple_of(2) {
+            return Err(error::parse_file::parse_node::ParseInputs::LayoutBroken {
+                offset: inputs_offset,
+            }
+            .into());
+        }
+        let inputs = inputs.chunks_exact(2).map(bytes_to_u16).collect();
+        if let Some(error) = self.parameter_error {
+            return Err(error.into());
+        }
+        let mut node = Node::new(NodeType::try_from(id), x, y, inputs);
+        if let Some((offset, bytes)) = self.value_type {
+            use error::parse_file::parse_node::ParseValueType;
+            node = node.with_value_type(match (bytes.first(), bytes.len()) {
+                (Some(0), 1) => ValueType::F32,
+                (Some(1), 1) => ValueType::F64,
+                (Some(2), 1) => ValueType::Bool,
+                (Some(3), 1) => ValueType::I64,
+                (Some(4), 4) => ValueType::Quantity(Dimension::new(
+                    bytes[1] as i8,
+                    bytes[2] as i8,
+                    bytes[3] as i8,
+                )),
+                (Some(0..=4), _) | (None, _) => {
+                    return Err(ParseValueType::IncorrectLength { offset }.into());
+                }
+                (Some(code), _) => {
+                    return Err(ParseValueType::UnknownType {
+                        offset,
+                        code: *code,
+                    }
+                    .into());
+                }
+            });
+        }
+        if let Some((offset, parameters)) = self.parameters {
+            node = node.with_parameters(parameters);
+            if let Some(Err(error)) = node.typed_parameters() {
+                return Err(error::parse_file::parse_node::ParseParameters::Schema {
+                    offset,
+                    error,
+                }
+                .into());
+            }
+        }
+        Ok(node)
     }
-    Ok(bytes_to_u16(&found_numbers))
 }
-fn find_and_parse_coordinates(
-    data: &[u8],
-) -> Result<(f64, f64), error::parse_file::parse_node::ParseCoordinates> {
-    let found = hunt_tag(data, tags_u8::COORDINATES);
-    let found = match found {
-        Some(x) => x,
-        None => return Err(error::parse_file::parse_node::ParseCoordinates::NotFound),
-    };
-    let found_numbers = hunt_numbers(found, Some(16));
-    if found_numbers.len() != 16 {
-        return Err(error::parse_file::parse_node::ParseCoordinates::IncorrectLength);
-    }
-    Ok((
-        bytes_to_f64(&found_numbers[0..=7]),
-        bytes_to_f64(&found_numbers[8..=15]),
-    ))
-}
-fn find_and_parse_inputs(
-    data: &[u8],
-) -> Result<Vec<u16>, error::parse_file::parse_node::ParseInputs> {
-    let input_section = hunt_tags(
-        data,
-        tags_u8::NODE_INPUT_LIST_START,
-        tags_u8::NODE_INPUT_LIST_END,
-    );
-    if input_section.len() == 0 {
-        return Ok(Vec::new());
-    }
-    if input_section.len() != 1 {
-        return Err(error::parse_file::parse_node::ParseInputs::MultipleInputSections);
-    }
-    let input_section = input_section[0];
-    let found_numbers = hunt_numbers(input_section, None);
-    if found_numbers.len() % 2 != 0 {
-        return Err(error::parse_file::parse_node::ParseInputs::LayoutBroken);
-    }
-    let mut inputs = Vec::<u16>::new();
-    for i in 0..(found_numbers.len() / 2) {
-        inputs.push(bytes_to_u16(&[
-            found_numbers[i * 2],
-            found_numbers[i * 2 + 1],
-        ]));
-    }
-    Ok(inputs)
-}
-fn parse_node(data: &[u8]) -> Result<Node, error::parse_file::ParseNode> {
-    let id = find_and_parse_node_id(data)?;
-    let (x, y) = find_and_parse_coordinates(data)?;
-    let inputs = find_and_parse_inputs(data)?;
-    Ok(Node {
-        id: NodeType::try_from(id),
-        x: x,
-        y: y,
-        inputs: inputs,
-    })
-}
-fn parse_nodes(data: &[u8]) -> Result<Vec<Node>, error::parse_file::ParseNode> {
-    let mut output = Vec::<Node>::new();
-    for block in hunt_tags(data, tags_u8::NODE_START, tags_u8::NODE_END) {
-        output.push(parse_node(block)?);
+//Parses everything in data from start onward. Offsets in errors count from the start of data.
+fn parse_node_section(data: &[u8], start: usize) -> Result<Vec<Node>, error::ParseFile> {
+    let mut section_depth = 0u32;
+    let mut section_nonempty = false;
+    let mut section_offset = 0;
+    let mut section_nodes: Result<Vec<Node>, error::ParseFile> = Ok(Vec::new());
+    let mut sections = 0usize;
+    //Only the first nonempty node section is used, but its errors should not be reported if
+    //there turns out to be another one.
+    let mut first_section = None;
+    let mut node_depth = 0u32;
+    let mut node_nonempty = false;
+    let mut node_offset = 0;
+    let mut node_index = 0;
+    let mut node = NodeBuilder::default();
+    let mut tokenizer = Tokenizer::new(&data[start..]);
+    for token in tokenizer.by_ref() {
+        let offset = start + token.range.start;
+        match token.kind {
+            TokenKind::Tag(Ok(Tag::NodeSectionStart)) if section_depth == 0 => {
+                section_depth = 1;
+                section_nonempty = false;
+                section_offset = offset;
+                section_nodes = Ok(Vec::new());
+                node_depth = 0;
+                node_index = 0;
+                continue;
+            }
+            TokenKind::Tag(Ok(Tag::NodeSectionEnd)) if section_depth == 1 => {
+                section_depth = 0;
+                if node_depth >= 1 && section_nodes.is_ok() {
+                    section_nodes = Err(error::ParseFile::UnterminatedNode {
+                        node: node_index,
+                        offset: node_offset,
+                    });
+                }
+                if section_nonempty {
+                    sections += 1;
+                    if sections > 1 {
+                        return Err(error::ParseFile::MultipleNodeSections {
+                            offset: section_offset,
+                        });
+                    }
+                    first_section = Some(core::mem::replace(&mut section_nodes, Ok(Vec::new())));
+                }
+                continue;
+            }
+            TokenKind::Tag(Ok(Tag::NodeSectionStart)) => section_depth += 1,
+            TokenKind::Tag(Ok(Tag::NodeSectionEnd)) if section_depth >= 2 => section_depth -= 1,
+            _ if section_depth == 0 => continue,
+            _ => {}
+        }
+        section_nonempty = true;
+        match token.kind {
+            TokenKind::Tag(Ok(Tag::NodeStart)) if node_depth == 0 => {
+                node_depth = 1;
+                node_nonempty = false;
+                node_offset = offset;
+                node = NodeBuilder::default();
+                continue;
+            }
+            TokenKind::Tag(Ok(Tag::NodeEnd)) if node_depth == 1 => {
+                node_depth = 0;
+                if node_nonempty {
+                    let node = core::mem::take(&mut node);
+                    let parsed = if node.input_depth >= 1 {
+                        Err(error::ParseFile::UnterminatedInputList {
+                            node: node_index,
+                            offset: node.input_list.0,
+                        })
+                    } else if node.parameter_depth >= 1 {
+                        Err(error::ParseFile::UnterminatedParameterList {
+                            node: node_index,
+                            offset: node.parameter_list.0,
+                        })
+                    } else {
+                        node.finish().map_err(|error| error::ParseFile::ParseNode {
+                            node: node_index,
+                            offset: node_offset,
+                            error,
+                        })
+                    };
+                    if let Ok(nodes) = &mut section_nodes {
+                        match parsed {
+                            Ok(parsed) => nodes.push(parsed),
+                            Err(error) => section_nodes = Err(error),
+                        }
+                    }
+                    node_index += 1;
+                }
+                continue;
+            }
+            TokenKind::Tag(Ok(Tag::NodeStart)) => node_depth += 1,
+            TokenKind::Tag(Ok(Tag::NodeEnd)) if node_depth >= 2 => node_depth -= 1,
+            _ => {}
+        }
+        if node_depth >= 1 {
+            node_nonempty = true;
+            node.feed(token.kind, token.bytes, offset);
+        }
+    }
+    if let Some(offset) = tokenizer.truncated_skip() {
+        return Err(error::ParseFile::TruncatedSkip {
+            offset: start + offset,
+        });
+    }
+    if section_depth >= 1 {
+        if node_depth >= 1 && node.input_depth >= 1 {
+            return Err(error::ParseFile::UnterminatedInputList {
+                node: node_index,
+                offset: node.input_list.0,
+            });
+        }
+        if node_depth >= 1 && node.parameter_depth >= 1 {
+            return Err(error::ParseFile::UnterminatedParameterList {
+                node: node_index,
+                offset: node.parameter_list.0,
+            });
+        }
+        if node_depth >= 1 {
+            return Err(error::ParseFile::UnterminatedNode {
+                node: node_index,
+                offset: node_offset,
+            });
+        }
+        return Err(error::ParseFile::UnterminatedNodeSection {
+            offset: section_offset,
+        });
+    }
+    match first_section {
+        Some(nodes) => nodes,
+        None => Ok(Vec::new()),
     }
-    Ok(output)
 }
+#[allow(clippy::ptr_arg)]
 pub fn read_file(data: &Vec<u8>) -> Result<Vec<Node>, error::ParseFile> {
+    read_file_with_policy(data, VersionPolicy::Strict)
+}
+pub fn read_file_with_policy(
+    data: &[u8],
+    policy: VersionPolicy,
+) -> Result<Vec<Node>, error::ParseFile> {
     if data.len() < 16 {
         return Err(error::ParseFile::LayoutBroken);
     }
     if data[0..12] != *b"rrtkstrmbldr" {
         return Err(error::ParseFile::MagicNumbers);
     }
-    let major = data[12];
-    if major > MAJOR {
+    if !policy.accepts(Version::new(data[12], data[13], data[14], data[15])) {
         return Err(error::ParseFile::Version);
     }
-    let minor = data[13];
-    if minor > MINOR {
-        return Err(error::ParseFile::Version);
-    }
-    let patch = data[14];
-    if patch > PATCH {
-        return Err(error::ParseFile::Version);
-    }
-    let pre = data[15];
-    if pre > PRE {
-        return Err(error::ParseFile::Version);
-    }
-    let node_sections = hunt_tags(
-        &data[16..],
-        tags_u8::NODE_SECTION_START,
-        tags_u8::NODE_SECTION_END,
-    );
-    if node_sections.len() > 1 {
-        return Err(error::ParseFile::MultipleNodeSections);
-    }
-    if node_sections.len() == 0 {
-        return Ok(Vec::new());
-    }
-    let node_section = node_sections[0];
-    match parse_nodes(node_section) {
-        Ok(parsed) => Ok(parsed),
-        Err(error) => Err(error::ParseFile::ParseNode(error)),
-    }
+    parse_node_section(data, 16)
 }
 mod file_start {
     use super::*;
     #[allow(unused)]
-    #[repr(packed)]
+    #[repr(C, packed)]
     struct FileStart([u8; 12], u8, u8, u8, u8);
     pub const FILE_START: [u8; 16] =
         unsafe { transmute(FileStart(*b"rrtkstrmbldr", MAJOR, MINOR, PATCH, PRE)) };
 }
 pub use file_start::FILE_START;
-pub fn build_file<'a, I: Iterator<Item = &'a Node> + ExactSizeIterator>(nodes: I) -> Vec<u8> {
+//SKIP_U8 covers up to 256 bytes and SKIP_U16 up to 65536 bytes. Anything longer is split into
+//several SKIP_U16 runs, which the reader joins back together.
+fn push_numbers(output: &mut Vec<u8>, numbers: &[u8]) {
+    if numbers.len() <= 256 {
+        if !numbers.is_empty() {
+            output.push(tags_u8::SKIP_U8);
+            output.push((numbers.len() - 1) as u8);
+            output.extend_from_slice(numbers);
+        }
+        return;
+    }
+    for chunk in numbers.chunks(65536) {
+        output.push(tags_u8::SKIP_U16);
+        output.extend(u16_to_bytes((chunk.len() - 1) as u16));
+        output.extend_from_slice(chunk);
+    }
+}
+fn push_node(output: &mut Vec<u8>, node: &Node) {
+    output.push(tags_u8::NODE_START);
+    output.push(tags_u8::NODE_ID);
+    output.push(tags_u8::SKIP_2);
+    output.extend(u16_to_bytes(node_type_result_to_u16(node.id)));
+    output.push(tags_u8::COORDINATES);
+    output.push(tags_u8::SKIP_16);
+    output.extend(f64_to_bytes(node.x));
+    output.extend(f64_to_bytes(node.y));
+    output.push(tags_u8::NODE_INPUT_LIST_START);
+    let inputs = node
+        .inputs
+        .iter()
+        .flat_map(|input| u16_to_bytes(*input))
+        .collect::<Vec<u8>>();
+    push_numbers(output, &inputs);
+    output.push(tags_u8::NODE_INPUT_LIST_END);
+    if let Some(parameters) = &node.parameters {
+        output.push(tags_u8::PARAMETER_LIST_START);
+        for parameter in parameters {
+            match parameter {
+                Parameter::Float(value) => {
+                    output.extend([tags_u8::PARAMETER_FLOAT, tags_u8::SKIP_8]);
+                    output.extend(f64_to_bytes(*value));
+                }
+                Parameter::Integer(value) => {
+                    output.extend([tags_u8::PARAMETER_INTEGER, tags_u8::SKIP_8]);
+                    output.extend(i64_to_bytes(*value));
+                }
+                Parameter::Bool(value) => {
+                    output.extend([tags_u8::PARAMETER_BOOL, tags_u8::SKIP_1, *value as u8]);
+                }
+                Parameter::Time(value) => {
+                    output.extend([tags_u8::PARAMETER_TIME, tags_u8::SKIP_8]);
+                    output.extend(i64_to_bytes(*value));
+                }
+            }
+        }
+        output.push(tags_u8::PARAMETER_LIST_END);
+    }
+    if let Some(value_type) = node.value_type {
+        output.push(tags_u8::VALUE_TYPE);
+        push_numbers(output, &value_type_to_bytes(value_type));
+    }
+    output.push(tags_u8::NODE_END);
+}
+fn start_file(node_count: usize, version: Version) -> Vec<u8> {
     //18 bytes for the magic numbers, version, and NODE_SECTION tags
     //26 bytes for each node ignoring inputs
     //This is a lower limit for the file size.
-    let mut output = Vec::with_capacity(18 + 26 * nodes.len());
-    output.extend(FILE_START);
+    let mut output = Vec::with_capacity(18 + 26 * node_count);
+    output.extend(b"rrtkstrmbldr");
+    output.extend([version.major, version.minor, version.patch, version.pre]);
     output.push(tags_u8::NODE_SECTION_START);
-    for node in nodes {
-        output.push(tags_u8::NODE_START);
-        output.push(tags_u8::NODE_ID);
-        output.push(tags_u8::SKIP_2);
-        output.extend(u16_to_bytes(node_type_result_to_u16(node.id)));
-        output.push(tags_u8::COORDINATES);
-        output.push(tags_u8::SKIP_16);
-        output.extend(f64_to_bytes(node.x));
-        output.extend(f64_to_bytes(node.y));
-        output.push(tags_u8::NODE_INPUT_LIST_START);
-        if node.inputs.len() >= 1 {
-            output.push(tags_u8::SKIP_U8);
-            output.push((node.inputs.len() * 2 - 1) as u8);
-            for input in &node.inputs {
-                output.extend(u16_to_bytes(*input));
-            }
+    output
+}
+//Parts of the file format which were added after version 1.0.0.0. A node which uses one of these
+//cannot be written to a file for an older version.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Feature {
+    Parameters,
+    ValueType,
+}
+impl Feature {
+    pub fn version(self) -> Version {
+        match self {
+            Self::Parameters | Self::ValueType => Version::new(1, 1, 0, 0),
+        }
+    }
+}
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Parameters => write!(f, "a parameter list"),
+            Self::ValueType => write!(f, "a value type"),
         }
-        output.push(tags_u8::NODE_INPUT_LIST_END);
-        output.push(tags_u8::NODE_END);
+    }
+}
+impl Node {
+    pub fn features(&self) -> Vec<Feature> {
+        let mut features = Vec::new();
+        if self.parameters.is_some() {
+            features.push(Feature::Parameters);
+        }
+        if self.value_type.is_some() {
+            features.push(Feature::ValueType);
+        }
+        features
+    }
+    ///The lowest specification version which can represent this node.
+    pub fn required_version(&self) -> Version {
+        self.features()
+            .into_iter()
+            .map(Feature::version)
+            .fold(Version::new(MAJOR, 0, 0, 0), Version::max)
+    }
+}
+//Files are stamped with the lowest version that can represent every node in them so that older
+//readers can still read files which do not use anything newer.
+fn stamp_version(output: &mut [u8], version: Version) {
+    output[12..16].copy_from_slice(&[version.major, version.minor, version.patch, version.pre]);
+}
+pub fn build_file<'a, I: Iterator<Item = &'a Node> + ExactSizeIterator>(nodes: I) -> Vec<u8> {
+    let mut output = start_file(nodes.len(), Version::CURRENT);
+    let mut version = Version::new(MAJOR, 0, 0, 0);
+    for node in nodes {
+        version = version.max(node.required_version());
+        push_node(&mut output, node);
     }
     output.push(tags_u8::NODE_SECTION_END);
+    stamp_version(&mut output, version);
     output
 }
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[test]
-    fn hunt_tags_() {
-        let data = vec![
-            1u8,
-            100,
-            2,
-            3,
-            101,
-            tags_u8::SKIP_1,
-            100,
-            tags_u8::SKIP_8,
-            100,
-            1,
-            2,
-            3,
-            4,
-            5,
-            6,
-            101,
-            //SKIP_8 tag ends here
-            4,
-            100,
-            5,
-            6,
-            7,
-            101,
-            8,
-            tags_u8::SKIP_U8,
-            2,
-            100,
-            4,
-            101,
-            //SKIP_U8 tag ends here
-            100,
-            8,
-            9,
-            101,
-            5,
-            tags_u8::SKIP_U16,
-            //Just a really hacky way of inserting a u16 into an array of u8s.
-            u16_to_bytes(3)[0],
-            u16_to_bytes(3)[1],
-            100,
-            2,
-            101,
-            3,
-            //SKIP_U16 tag ends here
-            100,
-            10,
-            11,
-            101,
-            6,
-        ];
-        let found = hunt_tags(&data, 100, 101);
-        assert_eq!(
-            found,
-            vec![&[2, 3][..], &[5, 6, 7][..], &[8, 9][..], &[10, 11][..]]
-        );
+pub fn try_build_file<'a, I: Iterator<Item = &'a Node> + ExactSizeIterator>(
+    nodes: I,
+) -> Result<Vec<u8>, error::BuildFile> {
+    let (mut output, required) = build_nodes(nodes, Version::CURRENT)?;
+    stamp_version(&mut output, required);
+    Ok(output)
+}
+pub fn try_build_file_for_version<'a, I: Iterator<Item = &'a Node> + ExactSizeIterator>(
+    nodes: I,
+    version: Version,
+) -> Result<Vec<u8>, error::BuildFile> {
+    build_nodes(nodes, version).map(|(output, _)| output)
+}
+//Builds a file stamped with version and also returns the lowest version its nodes need.
+fn build_nodes<'a, I: Iterator<Item = &'a Node> + ExactSizeIterator>(
+    nodes: I,
+    version: Version,
+) -> Result<(Vec<u8>, Version), error::BuildFile> {
+    if version > Version::CURRENT || version.major != MAJOR {
+        return Err(error::BuildFile::UnsupportedVersion(version));
+    }
+    let node_count = nodes.len();
+    //Inputs are u16 indices, so there is no way to refer to any more nodes than this.
+    if node_count > u16::MAX as usize + 1 {
+        return Err(error::BuildFile::TooManyNodes { count: node_count });
     }
+    let mut output = start_file(node_count, version);
+    let mut unsupported = Vec::new();
+    let mut required = Version::new(MAJOR, 0, 0, 0);
+    for (index, node) in nodes.enumerate() {
+        for (input, target) in node.inputs.iter().enumerate() {
+            if *target as usize >= node_count {
+                return Err(error::BuildFile::InputOutOfRange {
+                    node: index,
+                    input,
+                    target: *target,
+                });
+            }
+        }
+        for feature in node.features() {
+            if feature.version() > version {
+                unsupported.push(error::build_file::Unsupported {
+                    node: index,
+                    feature,
+                });
+            }
+        }
+        required = required.max(node.required_version());
+        push_node(&mut output, node);
+    }
+    if !unsupported.is_empty() {
+        return Err(error::BuildFile::Downconvert {
+            version,
+            unsupported,
+        });
+    }
+    output.push(tags_u8::NODE_SECTION_END);
+    Ok((output, required))
 }
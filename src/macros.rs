@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+///Builds a `Vec<Node>` from a list of named nodes. Each node is written as
+///`name = NodeType(inputs) [parameters] @ (x, y);` where the inputs are names of other nodes in
+///the list and the parameter list is optional. Leave out the parentheses for a node with no
+///inputs. Names are turned into indices at compile time, so they can refer to nodes later in the
+///list and a misspelled name is a compile error.
+///```
+///use rrtk_rsb::*;
+///let nodes = rsb_graph! {
+///    a = ConstantGetter [Parameter::Float(1.0)] @ (0, 0);
+///    b = Latest(c) @ (10, 0);
+///    c = Sum2(a, b) @ (20, 0);
+///};
+///assert_eq!(nodes[2].inputs, vec![0, 1]);
+///```
+#[macro_export]
+macro_rules! rsb_graph {
+    ($(
+        $name:ident = $node_type:ident
+        $(($($input:ident),* $(,)?))?
+        $([$($parameter:expr),* $(,)?])?
+        @ ($x:expr, $y:expr)
+    );* $(;)?) => {{
+        //Enum discriminants count up from 0 in the order the nodes are written.
+        #[allow(non_camel_case_types, dead_code)]
+        #[derive(Clone, Copy)]
+        #[repr(u16)]
+        enum RsbGraphNodeNames {
+            $($name),*
+        }
+        #[allow(unused_imports)]
+        use RsbGraphNodeNames::*;
+        vec![$({
+            #[allow(unused_mut)]
+            let mut node = $crate::Node::new(
+                Ok($crate::NodeType::$node_type),
+                $x as f64,
+                $y as f64,
+                vec![$($($input as u16),*)?],
+            );
+            $(node = node.with_parameters(vec![$($parameter),*]);)?
+            node
+        }),*]
+    }};
+}
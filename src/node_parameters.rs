@@ -0,0 +1,302 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+use super::*;
+//Exponents of the base units of a quantity. For example, a velocity in millimeters per second has
+//a length of 1 and a time of -1.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Dimension {
+    pub length: i8,
+    pub time: i8,
+    pub mass: i8,
+}
+impl Dimension {
+    pub const NONE: Self = Self::new(0, 0, 0);
+    pub const fn new(length: i8, time: i8, mass: i8) -> Self {
+        Self { length, time, mass }
+    }
+}
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "L^{} T^{} M^{}", self.length, self.time, self.mass)
+    }
+}
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeParameters {
+    ConstantGetter {
+        value: Parameter,
+    },
+    NoneGetter,
+    Expirer {
+        max_time_to_old: i64,
+    },
+    Latest,
+    CommandPID {
+        kp: f64,
+        ki: f64,
+        kd: f64,
+    },
+    EWMAStream {
+        smoothing_constant: f64,
+    },
+    MovingAverageStream {
+        window: i64,
+    },
+    PIDControllerStream {
+        setpoint: f64,
+        kp: f64,
+        ki: f64,
+        kd: f64,
+    },
+    PositionToState,
+    VelocityToState,
+    AccelerationToState,
+    NoneToError,
+    NoneToValue {
+        value: Parameter,
+    },
+    FloatToQuantity {
+        dimension: Dimension,
+    },
+    QuantityToFloat,
+    DimensionAdder {
+        dimension: Dimension,
+    },
+    DimensionRemover {
+        dimension: Dimension,
+    },
+    FreezeStream,
+    IfStream,
+    IfElseStream,
+    AndStream,
+    OrStream,
+    NotStream,
+    SumStream,
+    Sum2,
+    DifferenceStream,
+    ProductStream,
+    Product2,
+    QuotientStream,
+    ExponentStream,
+    DerivativeStream,
+    IntegralStream,
+}
+//Takes parameters off the front of a list while checking their types.
+struct Reader<'a> {
+    parameters: &'a [Parameter],
+    index: usize,
+}
+impl<'a> Reader<'a> {
+    fn new(parameters: &'a [Parameter]) -> Self {
+        Self {
+            parameters,
+            index: 0,
+        }
+    }
+    fn next(&mut self) -> Result<(usize, Parameter), error::ParameterSchema> {
+        let index = self.index;
+        match self.parameters.get(index) {
+            Some(parameter) => {
+                self.index += 1;
+                Ok((index, *parameter))
+            }
+            None => Err(error::ParameterSchema::Missing { index }),
+        }
+    }
+    fn float(&mut self) -> Result<f64, error::ParameterSchema> {
+        match self.next()? {
+            (_, Parameter::Float(value)) => Ok(value),
+            (index, _) => Err(error::ParameterSchema::WrongType { index }),
+        }
+    }
+    fn time(&mut self) -> Result<i64, error::ParameterSchema> {
+        match self.next()? {
+            (_, Parameter::Time(value)) => Ok(value),
+            (index, _) => Err(error::ParameterSchema::WrongType { index }),
+        }
+    }
+    fn value(&mut self) -> Result<Parameter, error::ParameterSchema> {
+        match self.next()? {
+            (index, Parameter::Time(_)) => Err(error::ParameterSchema::WrongType { index }),
+            (_, value) => Ok(value),
+        }
+    }
+    fn exponent(&mut self) -> Result<i8, error::ParameterSchema> {
+        match self.next()? {
+            (index, Parameter::Integer(value)) => {
+                i8::try_from(value).map_err(|_| error::ParameterSchema::OutOfRange { index })
+            }
+            (index, _) => Err(error::ParameterSchema::WrongType { index }),
+        }
+    }
+    fn dimension(&mut self) -> Result<Dimension, error::ParameterSchema> {
+        Ok(Dimension::new(
+            self.exponent()?,
+            self.exponent()?,
+            self.exponent()?,
+        ))
+    }
+    fn finish(self) -> Result<(), error::ParameterSchema> {
+        if self.index < self.parameters.len() {
+            return Err(error::ParameterSchema::Extra { index: self.index });
+        }
+        Ok(())
+    }
+}
+fn dimension_to_parameters(dimension: Dimension) -> Vec<Parameter> {
+    vec![
+        Parameter::Integer(dimension.length as i64),
+        Parameter::Integer(dimension.time as i64),
+        Parameter::Integer(dimension.mass as i64),
+    ]
+}
+impl NodeParameters {
+    pub fn from_parameters(
+        node_type: NodeType,
+        parameters: &[Parameter],
+    ) -> Result<Self, error::ParameterSchema> {
+        let mut reader = Reader::new(parameters);
+        let output = match node_type {
+            NodeType::ConstantGetter => Self::ConstantGetter {
+                value: reader.value()?,
+            },
+            NodeType::NoneGetter => Self::NoneGetter,
+            NodeType::Expirer => Self::Expirer {
+                max_time_to_old: reader.time()?,
+            },
+            NodeType::Latest => Self::Latest,
+            NodeType::CommandPID => Self::CommandPID {
+                kp: reader.float()?,
+                ki: reader.float()?,
+                kd: reader.float()?,
+            },
+            NodeType::EWMAStream => Self::EWMAStream {
+                smoothing_constant: reader.float()?,
+            },
+            NodeType::MovingAverageStream => Self::MovingAverageStream {
+                window: reader.time()?,
+            },
+            NodeType::PIDControllerStream => Self::PIDControllerStream {
+                setpoint: reader.float()?,
+                kp: reader.float()?,
+                ki: reader.float()?,
+                kd: reader.float()?,
+            },
+            NodeType::PositionToState => Self::PositionToState,
+            NodeType::VelocityToState => Self::VelocityToState,
+            NodeType::AccelerationToState => Self::AccelerationToState,
+            NodeType::NoneToError => Self::NoneToError,
+            NodeType::NoneToValue => Self::NoneToValue {
+                value: reader.value()?,
+            },
+            NodeType::FloatToQuantity => Self::FloatToQuantity {
+                dimension: reader.dimension()?,
+            },
+            NodeType::QuantityToFloat => Self::QuantityToFloat,
+            NodeType::DimensionAdder => Self::DimensionAdder {
+                dimension: reader.dimension()?,
+            },
+            NodeType::DimensionRemover => Self::DimensionRemover {
+                dimension: reader.dimension()?,
+            },
+            NodeType::FreezeStream => Self::FreezeStream,
+            NodeType::IfStream => Self::IfStream,
+            NodeType::IfElseStream => Self::IfElseStream,
+            NodeType::AndStream => Self::AndStream,
+            NodeType::OrStream => Self::OrStream,
+            NodeType::NotStream => Self::NotStream,
+            NodeType::SumStream => Self::SumStream,
+            NodeType::Sum2 => Self::Sum2,
+            NodeType::DifferenceStream => Self::DifferenceStream,
+            NodeType::ProductStream => Self::ProductStream,
+            NodeType::Product2 => Self::Product2,
+            NodeType::QuotientStream => Self::QuotientStream,
+            NodeType::ExponentStream => Self::ExponentStream,
+            NodeType::DerivativeStream => Self::DerivativeStream,
+            NodeType::IntegralStream => Self::IntegralStream,
+        };
+        reader.finish()?;
+        Ok(output)
+    }
+    pub fn to_parameters(&self) -> Vec<Parameter> {
+        match *self {
+            Self::ConstantGetter { value } | Self::NoneToValue { value } => vec![value],
+            Self::Expirer { max_time_to_old } => vec![Parameter::Time(max_time_to_old)],
+            Self::CommandPID { kp, ki, kd } => vec![
+                Parameter::Float(kp),
+                Parameter::Float(ki),
+                Parameter::Float(kd),
+            ],
+            Self::EWMAStream { smoothing_constant } => vec![Parameter::Float(smoothing_constant)],
+            Self::MovingAverageStream { window } => vec![Parameter::Time(window)],
+            Self::PIDControllerStream {
+                setpoint,
+                kp,
+                ki,
+                kd,
+            } => vec![
+                Parameter::Float(setpoint),
+                Parameter::Float(kp),
+                Parameter::Float(ki),
+                Parameter::Float(kd),
+            ],
+            Self::FloatToQuantity { dimension }
+            | Self::DimensionAdder { dimension }
+            | Self::DimensionRemover { dimension } => dimension_to_parameters(dimension),
+            _ => Vec::new(),
+        }
+    }
+    pub fn node_type(&self) -> NodeType {
+        match self {
+            Self::ConstantGetter { .. } => NodeType::ConstantGetter,
+            Self::NoneGetter => NodeType::NoneGetter,
+            Self::Expirer { .. } => NodeType::Expirer,
+            Self::Latest => NodeType::Latest,
+            Self::CommandPID { .. } => NodeType::CommandPID,
+            Self::EWMAStream { .. } => NodeType::EWMAStream,
+            Self::MovingAverageStream { .. } => NodeType::MovingAverageStream,
+            Self::PIDControllerStream { .. } => NodeType::PIDControllerStream,
+            Self::PositionToState => NodeType::PositionToState,
+            Self::VelocityToState => NodeType::VelocityToState,
+            Self::AccelerationToState => NodeType::AccelerationToState,
+            Self::NoneToError => NodeType::NoneToError,
+            Self::NoneToValue { .. } => NodeType::NoneToValue,
+            Self::FloatToQuantity { .. } => NodeType::FloatToQuantity,
+            Self::QuantityToFloat => NodeType::QuantityToFloat,
+            Self::DimensionAdder { .. } => NodeType::DimensionAdder,
+            Self::DimensionRemover { .. } => NodeType::DimensionRemover,
+            Self::FreezeStream => NodeType::FreezeStream,
+            Self::IfStream => NodeType::IfStream,
+            Self::IfElseStream => NodeType::IfElseStream,
+            Self::AndStream => NodeType::AndStream,
+            Self::OrStream => NodeType::OrStream,
+            Self::NotStream => NodeType::NotStream,
+            Self::SumStream => NodeType::SumStream,
+            Self::Sum2 => NodeType::Sum2,
+            Self::DifferenceStream => NodeType::DifferenceStream,
+            Self::ProductStream => NodeType::ProductStream,
+            Self::Product2 => NodeType::Product2,
+            Self::QuotientStream => NodeType::QuotientStream,
+            Self::ExponentStream => NodeType::ExponentStream,
+            Self::DerivativeStream => NodeType::DerivativeStream,
+            Self::IntegralStream => NodeType::IntegralStream,
+        }
+    }
+}
+impl Node {
+    pub fn from_typed_parameters(
+        parameters: NodeParameters,
+        x: f64,
+        y: f64,
+        inputs: Vec<u16>,
+    ) -> Self {
+        Self::new(Ok(parameters.node_type()), x, y, inputs)
+            .with_parameters(parameters.to_parameters())
+    }
+    //None if the node type is unknown or the node has no parameter list
+    pub fn typed_parameters(&self) -> Option<Result<NodeParameters, error::ParameterSchema>> {
+        let node_type = self.id.ok()?;
+        let parameters = self.parameters.as_ref()?;
+        Some(NodeParameters::from_parameters(node_type, parameters))
+    }
+}
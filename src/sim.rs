@@ -0,0 +1,534 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+//A discrete-time simulator which evaluates a node graph at a fixed timestep. Node types without
+//a reference behavior here always output None. CommandPID commands are not stored in the file, so
+//they are given with Simulator::set_command.
+use super::*;
+mod plant;
+pub use plant::{DcMotor, FirstOrderLag, Integrator, Measurement, Plant};
+mod command;
+pub use command::{Command, PositionDerivative, State};
+mod response;
+pub use response::{StepResponse, step_response};
+mod csv;
+pub use csv::{CsvError, read_csv, write_csv};
+mod trace;
+pub use trace::{Recorder, Samples, SimulateError, read_traces, simulate};
+const NANOSECONDS_PER_SECOND: f64 = 1_000_000_000.0;
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    //Quantities are floats here. Their dimensions are only checked statically.
+    Float(f64),
+    Integer(i64),
+    Bool(bool),
+    State(State),
+}
+impl From<Parameter> for Value {
+    fn from(was: Parameter) -> Self {
+        match was {
+            Parameter::Float(value) => Self::Float(value),
+            Parameter::Integer(value) | Parameter::Time(value) => Self::Integer(value),
+            Parameter::Bool(value) => Self::Bool(value),
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Datum {
+    //In nanoseconds, on the same clock as Simulator::time
+    pub time: i64,
+    pub value: Value,
+}
+impl Datum {
+    pub fn new(time: i64, value: Value) -> Self {
+        Self { time, value }
+    }
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SimError {
+    //A NoneToError node got None.
+    FromNone,
+    //A node got a value of a type it can't work with, like a float and an integer to add.
+    WrongType,
+    //Integer arithmetic overflowed.
+    Overflow,
+    //An integer was divided by zero.
+    DivideByZero,
+    //An integer was raised to a negative power, which is not an integer.
+    NegativeExponent,
+    //Stepping would take the time past i64::MAX.
+    TimeOverflow,
+}
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::FromNone => write!(f, "got None where a value was required"),
+            Self::WrongType => write!(f, "got a value of the wrong type"),
+            Self::Overflow => write!(f, "integer arithmetic overflowed"),
+            Self::DivideByZero => write!(f, "integer divided by zero"),
+            Self::NegativeExponent => write!(f, "integer raised to a negative power"),
+            Self::TimeOverflow => write!(f, "simulation time overflowed"),
+        }
+    }
+}
+impl core::error::Error for SimError {}
+//Returned when setting up a node which is not in the simulation
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NodeNotFound {
+    pub node: usize,
+}
+impl fmt::Display for NodeNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "node {} is not in the simulation", self.node)
+    }
+}
+impl core::error::Error for NodeNotFound {}
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SimulatorError {
+    Cycle(CycleError),
+    //Time would never advance or would run backwards.
+    InvalidTimestep(i64),
+}
+impl fmt::Display for SimulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Cycle(_) => write!(f, "graph cannot be evaluated"),
+            Self::InvalidTimestep(timestep) => {
+                write!(f, "timestep {} is not positive", timestep)
+            }
+        }
+    }
+}
+impl core::error::Error for SimulatorError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Cycle(error) => Some(error),
+            Self::InvalidTimestep(_) => None,
+        }
+    }
+}
+//What a node outputs at one step, like the Output type of an RRTK stream
+pub type Output = Result<Option<Datum>, SimError>;
+fn arithmetic(
+    a: Value,
+    b: Value,
+    float: fn(f64, f64) -> f64,
+    integer: fn(i64, i64) -> Option<i64>,
+) -> Result<Value, SimError> {
+    match (a, b) {
+        (Value::Float(a), Value::Float(b)) => Ok(Value::Float(float(a, b))),
+        (Value::Integer(a), Value::Integer(b)) => {
+            integer(a, b).map(Value::Integer).ok_or(SimError::Overflow)
+        }
+        _ => Err(SimError::WrongType),
+    }
+}
+fn add(a: Value, b: Value) -> Result<Value, SimError> {
+    arithmetic(a, b, |a, b| a + b, i64::checked_add)
+}
+fn subtract(a: Value, b: Value) -> Result<Value, SimError> {
+    arithmetic(a, b, |a, b| a - b, i64::checked_sub)
+}
+fn multiply(a: Value, b: Value) -> Result<Value, SimError> {
+    arithmetic(a, b, |a, b| a * b, i64::checked_mul)
+}
+fn divide(a: Value, b: Value) -> Result<Value, SimError> {
+    if let (Value::Integer(_), Value::Integer(0)) = (a, b) {
+        return Err(SimError::DivideByZero);
+    }
+    arithmetic(a, b, |a, b| a / b, i64::checked_div)
+}
+fn power(a: Value, b: Value) -> Result<Value, SimError> {
+    if let (Value::Integer(_), Value::Integer(..0)) = (a, b) {
+        return Err(SimError::NegativeExponent);
+    }
+    arithmetic(a, b, f64::powf, |a, b| a.checked_pow(b.try_into().ok()?))
+}
+//Errors from either input come first. The result is None if either input is and otherwise has
+//the later of the two timestamps.
+fn binary(a: Output, b: Output, combine: fn(Value, Value) -> Result<Value, SimError>) -> Output {
+    let (Some(a), Some(b)) = (a?, b?) else {
+        return Ok(None);
+    };
+    Ok(Some(Datum::new(
+        a.time.max(b.time),
+        combine(a.value, b.value)?,
+    )))
+}
+fn float_value(datum: Datum) -> Result<f64, SimError> {
+    match datum.value {
+        Value::Float(value) => Ok(value),
+        _ => Err(SimError::WrongType),
+    }
+}
+fn bool_value(output: Output) -> Result<Option<bool>, SimError> {
+    match output? {
+        Some(Datum {
+            value: Value::Bool(value),
+            ..
+        }) => Ok(Some(value)),
+        Some(_) => Err(SimError::WrongType),
+        None => Ok(None),
+    }
+}
+//What nodes which depend on their earlier inputs keep between steps
+#[derive(Clone, Copy, Debug, Default)]
+struct Memory {
+    //The last input, or the last error for PIDControllerStream
+    previous: Option<Datum>,
+    //The integral so far
+    accumulator: f64,
+    //The last velocity for PositionToState
+    previous_derivative: Option<f64>,
+    //Further integrals for AccelerationToState and CommandPID
+    integrals: [f64; 2],
+    //The command for CommandPID
+    command: Option<Command>,
+}
+#[derive(Debug)]
+struct PlantBinding {
+    plant: Box<dyn Plant>,
+    control: usize,
+    sensor: usize,
+}
+#[derive(Debug)]
+pub struct Simulator {
+    nodes: Vec<Node>,
+    order: Vec<usize>,
+    timestep: i64,
+    time: i64,
+    outputs: Vec<Output>,
+    //Nodes whose outputs are set from outside and not evaluated
+    external: Vec<bool>,
+    memory: Vec<Memory>,
+    plants: Vec<PlantBinding>,
+    traces: Vec<trace::Trace>,
+}
+impl Simulator {
+    //timestep is in nanoseconds and must be positive. Time starts at 0 and every node outputs None
+    //until the first step.
+    pub fn new(nodes: Vec<Node>, timestep: i64) -> Result<Self, SimulatorError> {
+        if timestep <= 0 {
+            return Err(SimulatorError::InvalidTimestep(timestep));
+        }
+        let order = evaluation_order(&nodes).map_err(SimulatorError::Cycle)?;
+        Ok(Self {
+            outputs: vec![Ok(None); nodes.len()],
+            external: vec![false; nodes.len()],
+            memory: vec![Memory::default(); nodes.len()],
+            plants: Vec::new(),
+            traces: Vec::new(),
+            nodes,
+            order,
+            timestep,
+            time: 0,
+        })
+    }
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+    pub fn timestep(&self) -> i64 {
+        self.timestep
+    }
+    //In nanoseconds
+    pub fn time(&self) -> i64 {
+        self.time
+    }
+    //Nodes which do not exist output None.
+    pub fn output(&self, node: usize) -> Output {
+        self.outputs.get(node).copied().unwrap_or(Ok(None))
+    }
+    pub fn outputs(&self) -> &[Output] {
+        &self.outputs
+    }
+    fn check_node(&self, node: usize) -> Result<(), NodeNotFound> {
+        match node < self.nodes.len() {
+            true => Ok(()),
+            false => Err(NodeNotFound { node }),
+        }
+    }
+    //Makes the node output this until it is set again or released instead of evaluating it. This
+    //is for sensors and other sources outside of the graph.
+    pub fn set_output(&mut self, node: usize, output: Output) -> Result<(), NodeNotFound> {
+        self.check_node(node)?;
+        self.outputs[node] = output;
+        self.external[node] = true;
+        Ok(())
+    }
+    //Sets the node's output to value with the current time as its timestamp.
+    pub fn set_value(&mut self, node: usize, value: Value) -> Result<(), NodeNotFound> {
+        self.set_output(node, Ok(Some(Datum::new(self.time, value))))
+    }
+    //Goes back to evaluating the node normally from the next step.
+    pub fn release(&mut self, node: usize) -> Result<(), NodeNotFound> {
+        self.check_node(node)?;
+        self.external[node] = false;
+        Ok(())
+    }
+    //At the start of every step, the plant is advanced by one timestep using the control node's
+    //output from the step before, and its reading is set as the output of the sensor node. The
+    //control input is 0 while the control node does not output a float.
+    pub fn add_plant(
+        &mut self,
+        plant: impl Plant + 'static,
+        control: usize,
+        sensor: usize,
+    ) -> Result<(), NodeNotFound> {
+        self.check_node(control)?;
+        self.check_node(sensor)?;
+        self.external[sensor] = true;
+        self.plants.push(PlantBinding {
+            plant: Box::new(plant),
+            control,
+            sensor,
+        });
+        Ok(())
+    }
+    //Seconds between the node's last input and this one, or None if there is no new input
+    fn input_interval(&self, node: usize, datum: Datum) -> Option<Option<f64>> {
+        match self.memory[node].previous {
+            Some(previous) if datum.time <= previous.time => None,
+            Some(previous) => Some(Some(
+                (datum.time - previous.time) as f64 / NANOSECONDS_PER_SECOND,
+            )),
+            None => Some(None),
+        }
+    }
+    //Times are in seconds for the integral and derivative like in RRTK.
+    fn pid_controller(&mut self, node: usize) -> Output {
+        let Some(Ok(NodeParameters::PIDControllerStream {
+            setpoint,
+            kp,
+            ki,
+            kd,
+        })) = self.nodes[node].typed_parameters()
+        else {
+            return Ok(None);
+        };
+        let Some(datum) = self.input(node, 0)? else {
+            return Ok(None);
+        };
+        let error = setpoint - float_value(datum)?;
+        let Some(interval) = self.input_interval(node, datum) else {
+            return self.output(node);
+        };
+        let memory = &mut self.memory[node];
+        let derivative = match (interval, memory.previous) {
+            (Some(interval), Some(previous)) => {
+                let previous = float_value(previous)?;
+                memory.accumulator += (previous + error) / 2.0 * interval;
+                (error - previous) / interval
+            }
+            _ => 0.0,
+        };
+        memory.previous = Some(Datum::new(datum.time, Value::Float(error)));
+        let output = kp * error + ki * memory.accumulator + kd * derivative;
+        Ok(Some(Datum::new(datum.time, Value::Float(output))))
+    }
+    //The derivative is None until there have been two inputs. The integral starts at 0.
+    fn calculus(&mut self, node: usize, derivative: bool) -> Output {
+        let Some(datum) = self.input(node, 0)? else {
+            return Ok(None);
+        };
+        let value = float_value(datum)?;
+        let Some(interval) = self.input_interval(node, datum) else {
+            return self.output(node);
+        };
+        let memory = &mut self.memory[node];
+        let previous = memory.previous.replace(datum);
+        let output = match (interval, previous) {
+            (Some(interval), Some(previous)) => {
+                let previous = float_value(previous)?;
+                memory.accumulator += (previous + value) / 2.0 * interval;
+                match derivative {
+                    true => (value - previous) / interval,
+                    false => memory.accumulator,
+                }
+            }
+            _ if derivative => return Ok(None),
+            _ => 0.0,
+        };
+        Ok(Some(Datum::new(datum.time, Value::Float(output))))
+    }
+    fn update(&mut self, node: usize) -> Output {
+        match self.nodes[node].id {
+            Ok(NodeType::PIDControllerStream) => self.pid_controller(node),
+            Ok(NodeType::DerivativeStream) => self.calculus(node, true),
+            Ok(NodeType::IntegralStream) => self.calculus(node, false),
+            Ok(NodeType::CommandPID) => self.command_pid(node),
+            Ok(NodeType::PositionToState) => self.state_from(node, PositionDerivative::Position),
+            Ok(NodeType::VelocityToState) => self.state_from(node, PositionDerivative::Velocity),
+            Ok(NodeType::AccelerationToState) => {
+                self.state_from(node, PositionDerivative::Acceleration)
+            }
+            _ => self.evaluate(node),
+        }
+    }
+    fn input(&self, node: usize, input: usize) -> Output {
+        match self.nodes[node].inputs.get(input) {
+            Some(input) => self.output(*input as usize),
+            None => Ok(None),
+        }
+    }
+    //Combines every input which is not None, or None if all of them are.
+    fn fold_inputs(
+        &self,
+        node: usize,
+        combine: fn(Value, Value) -> Result<Value, SimError>,
+    ) -> Output {
+        let mut output: Option<Datum> = None;
+        for input in 0..self.nodes[node].inputs.len() {
+            let Some(datum) = self.input(node, input)? else {
+                continue;
+            };
+            output = Some(match output {
+                Some(output) => Datum::new(
+                    output.time.max(datum.time),
+                    combine(output.value, datum.value)?,
+                ),
+                None => datum,
+            });
+        }
+        Ok(output)
+    }
+    //The value of a ConstantGetter or NoneToValue parameter
+    fn parameter_value(&self, node: usize) -> Option<Value> {
+        match self.nodes[node].typed_parameters()? {
+            Ok(
+                NodeParameters::ConstantGetter { value } | NodeParameters::NoneToValue { value },
+            ) => Some(value.into()),
+            _ => None,
+        }
+    }
+    fn evaluate(&self, node: usize) -> Output {
+        let input = |index| self.input(node, index);
+        let Ok(node_type) = self.nodes[node].id else {
+            return Ok(None);
+        };
+        match node_type {
+            NodeType::ConstantGetter => Ok(self
+                .parameter_value(node)
+                .map(|value| Datum::new(self.time, value))),
+            NodeType::NoneGetter => Ok(None),
+            //Quantities are floats here, so these change nothing.
+            NodeType::FloatToQuantity
+            | NodeType::QuantityToFloat
+            | NodeType::DimensionAdder
+            | NodeType::DimensionRemover => input(0),
+            NodeType::Expirer => {
+                let max_time_to_old = match self.nodes[node].typed_parameters() {
+                    Some(Ok(NodeParameters::Expirer { max_time_to_old })) => max_time_to_old,
+                    _ => return Ok(None),
+                };
+                match input(0)? {
+                    Some(datum) if self.time - datum.time > max_time_to_old => Ok(None),
+                    output => Ok(output),
+                }
+            }
+            //The input with the latest timestamp wins. If no input has a value, this is the first
+            //error if there is one.
+            NodeType::Latest => {
+                let mut output: Output = Ok(None);
+                for index in 0..self.nodes[node].inputs.len() {
+                    output = match (output, input(index)) {
+                        (Ok(Some(latest)), Ok(Some(datum))) if datum.time <= latest.time => {
+                            Ok(Some(latest))
+                        }
+                        (Ok(Some(latest)), Err(_)) => Ok(Some(latest)),
+                        (Err(error), Ok(None) | Err(_)) => Err(error),
+                        (_, new) => new,
+                    };
+                }
+                output
+            }
+            //While the freezer is true, this keeps outputting what it did before it froze.
+            NodeType::FreezeStream => match bool_value(input(1))? {
+                Some(true) => self.output(node),
+                _ => input(0),
+            },
+            NodeType::NoneToError => match input(0)? {
+                Some(datum) => Ok(Some(datum)),
+                None => Err(SimError::FromNone),
+            },
+            NodeType::NoneToValue => match input(0)? {
+                Some(datum) => Ok(Some(datum)),
+                None => Ok(self
+                    .parameter_value(node)
+                    .map(|value| Datum::new(self.time, value))),
+            },
+            NodeType::SumStream => self.fold_inputs(node, add),
+            NodeType::ProductStream => self.fold_inputs(node, multiply),
+            NodeType::Sum2 => binary(input(0), input(1), add),
+            NodeType::DifferenceStream => binary(input(0), input(1), subtract),
+            NodeType::Product2 => binary(input(0), input(1), multiply),
+            NodeType::QuotientStream => binary(input(0), input(1), divide),
+            NodeType::ExponentStream => binary(input(0), input(1), power),
+            //These are false if either input is false even if the other is None, and likewise
+            //for true with OrStream.
+            NodeType::AndStream | NodeType::OrStream => {
+                let short_circuit = node_type == NodeType::OrStream;
+                let (a, b) = (input(0), input(1));
+                let time = match (a?, b?) {
+                    (Some(a), Some(b)) => a.time.max(b.time),
+                    (Some(datum), None) | (None, Some(datum)) => datum.time,
+                    (None, None) => return Ok(None),
+                };
+                let value = match (bool_value(a)?, bool_value(b)?) {
+                    (Some(a), _) | (_, Some(a)) if a == short_circuit => short_circuit,
+                    (Some(_), Some(_)) => !short_circuit,
+                    _ => return Ok(None),
+                };
+                Ok(Some(Datum::new(time, Value::Bool(value))))
+            }
+            NodeType::NotStream => match (input(0)?, bool_value(input(0))?) {
+                (Some(datum), Some(value)) => Ok(Some(Datum::new(datum.time, Value::Bool(!value)))),
+                _ => Ok(None),
+            },
+            NodeType::IfStream => match bool_value(input(0))? {
+                Some(true) => input(1),
+                _ => Ok(None),
+            },
+            NodeType::IfElseStream => match bool_value(input(0))? {
+                Some(true) => input(1),
+                Some(false) => input(2),
+                None => Ok(None),
+            },
+            _ => Ok(None),
+        }
+    }
+    //Advances time by one timestep and updates every node in evaluation order. Nothing changes if
+    //the time would overflow.
+    pub fn step(&mut self) -> Result<(), SimError> {
+        self.time = self
+            .time
+            .checked_add(self.timestep)
+            .ok_or(SimError::TimeOverflow)?;
+        let interval = self.timestep as f64 / NANOSECONDS_PER_SECOND;
+        for binding in &mut self.plants {
+            let control = match self.outputs[binding.control] {
+                Ok(Some(Datum {
+                    value: Value::Float(control),
+                    ..
+                })) => control,
+                _ => 0.0,
+            };
+            let reading = binding.plant.update(control, interval);
+            self.outputs[binding.sensor] = Ok(Some(Datum::new(self.time, Value::Float(reading))));
+        }
+        for trace in &mut self.traces {
+            while let Some((time, value)) = trace.samples.get(trace.next) {
+                if *time > self.time {
+                    break;
+                }
+                self.outputs[trace.node] = Ok(Some(Datum::new(*time, *value)));
+                trace.next += 1;
+            }
+        }
+        for position in 0..self.order.len() {
+            let node = self.order[position];
+            if !self.external[node] {
+                self.outputs[node] = self.update(node);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+//State nodes and CommandPID. Like in RRTK, CommandPID runs PID on the error in whichever of
+//position, velocity and acceleration its command is for. Its output is that PID output for a
+//position command, its integral for a velocity command and its double integral for an
+//acceleration command.
+use super::*;
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PositionDerivative {
+    Position,
+    Velocity,
+    Acceleration,
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct State {
+    pub position: f64,
+    pub velocity: f64,
+    pub acceleration: f64,
+}
+impl State {
+    pub fn new(position: f64, velocity: f64, acceleration: f64) -> Self {
+        Self {
+            position,
+            velocity,
+            acceleration,
+        }
+    }
+    pub fn get(&self, derivative: PositionDerivative) -> f64 {
+        match derivative {
+            PositionDerivative::Position => self.position,
+            PositionDerivative::Velocity => self.velocity,
+            PositionDerivative::Acceleration => self.acceleration,
+        }
+    }
+}
+//What a CommandPID node should make its state input follow. Commands are not stored in the file,
+//so they are set on the simulator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Command {
+    pub derivative: PositionDerivative,
+    pub value: f64,
+}
+impl Command {
+    pub fn new(derivative: PositionDerivative, value: f64) -> Self {
+        Self { derivative, value }
+    }
+}
+impl Simulator {
+    //Sets the command of a CommandPID node. Changing which derivative is commanded starts the
+    //controller over. Nothing happens for other node types.
+    pub fn set_command(&mut self, node: usize, command: Command) -> Result<(), NodeNotFound> {
+        self.check_node(node)?;
+        let memory = &mut self.memory[node];
+        if memory.command.map(|old| old.derivative) != Some(command.derivative) {
+            *memory = Memory::default();
+        }
+        memory.command = Some(command);
+        Ok(())
+    }
+    //Builds a state from a position, velocity or acceleration input by differentiating and
+    //integrating it. Anything that can't be worked out yet is 0.
+    pub(super) fn state_from(&mut self, node: usize, given: PositionDerivative) -> Output {
+        let Some(datum) = self.input(node, 0)? else {
+            return Ok(None);
+        };
+        let value = float_value(datum)?;
+        let Some(interval) = self.input_interval(node, datum) else {
+            return self.output(node);
+        };
+        let memory = &mut self.memory[node];
+        let previous = memory.previous.replace(datum);
+        let (Some(interval), Some(previous)) = (interval, previous) else {
+            let state = match given {
+                PositionDerivative::Position => State::new(value, 0.0, 0.0),
+                PositionDerivative::Velocity => State::new(0.0, value, 0.0),
+                PositionDerivative::Acceleration => State::new(0.0, 0.0, value),
+            };
+            return Ok(Some(Datum::new(datum.time, Value::State(state))));
+        };
+        let previous = float_value(previous)?;
+        let state = match given {
+            PositionDerivative::Position => {
+                let velocity = (value - previous) / interval;
+                let acceleration = match memory.previous_derivative.replace(velocity) {
+                    Some(previous_velocity) => (velocity - previous_velocity) / interval,
+                    None => 0.0,
+                };
+                State::new(value, velocity, acceleration)
+            }
+            PositionDerivative::Velocity => {
+                memory.accumulator += (previous + value) / 2.0 * interval;
+                State::new(memory.accumulator, value, (value - previous) / interval)
+            }
+            PositionDerivative::Acceleration => {
+                let previous_velocity = memory.accumulator;
+                memory.accumulator += (previous + value) / 2.0 * interval;
+                memory.integrals[0] += (previous_velocity + memory.accumulator) / 2.0 * interval;
+                State::new(memory.integrals[0], memory.accumulator, value)
+            }
+        };
+        Ok(Some(Datum::new(datum.time, Value::State(state))))
+    }
+    //The output is the control effort as a float rather than a command so that it can drive a
+    //plant. It is None until a command is set. Times are in seconds like in pid_controller.
+    pub(super) fn command_pid(&mut self, node: usize) -> Output {
+        let Some(Ok(NodeParameters::CommandPID { kp, ki, kd })) =
+            self.nodes[node].typed_parameters()
+        else {
+            return Ok(None);
+        };
+        let Some(command) = self.memory[node].command else {
+            return Ok(None);
+        };
+        let Some(datum) = self.input(node, 0)? else {
+            return Ok(None);
+        };
+        let Value::State(state) = datum.value else {
+            return Err(SimError::WrongType);
+        };
+        let error = command.value - state.get(command.derivative);
+        let Some(interval) = self.input_interval(node, datum) else {
+            return self.output(node);
+        };
+        let memory = &mut self.memory[node];
+        let derivative = match (interval, memory.previous) {
+            (Some(interval), Some(previous)) => {
+                let previous = float_value(previous)?;
+                memory.accumulator += (previous + error) / 2.0 * interval;
+                (error - previous) / interval
+            }
+            _ => 0.0,
+        };
+        memory.previous = Some(Datum::new(datum.time, Value::Float(error)));
+        let mut output = kp * error + ki * memory.accumulator + kd * derivative;
+        let integrations = match command.derivative {
+            PositionDerivative::Position => 0,
+            PositionDerivative::Velocity => 1,
+            PositionDerivative::Acceleration => 2,
+        };
+        for integral in &mut memory.integrals[..integrations] {
+            *integral += output * interval.unwrap_or(0.0);
+            output = *integral;
+        }
+        Ok(Some(Datum::new(datum.time, Value::Float(output))))
+    }
+}
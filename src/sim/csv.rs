@@ -0,0 +1,104 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+//Just enough CSV to read and write traces. Fields can be quoted with " and a quote inside a
+//quoted field is written as "".
+use super::*;
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CsvError {
+    //Lines start at 1.
+    UnterminatedQuote { line: usize },
+    //A quote in the middle of a field which is not quoted, or text after a closing quote
+    MisplacedQuote { line: usize },
+}
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnterminatedQuote { line } => {
+                write!(f, "quoted field starting on line {} never ends", line)
+            }
+            Self::MisplacedQuote { line } => write!(f, "misplaced quote on line {}", line),
+        }
+    }
+}
+impl core::error::Error for CsvError {}
+//Returns each row with its fields. Lines with nothing on them are skipped.
+pub fn read_csv(text: &str) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    //Whether the current field was quoted, and the line its quote started on
+    let mut quoted = None;
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut characters = text.chars().peekable();
+    while let Some(character) = characters.next() {
+        if in_quotes {
+            match character {
+                '"' if characters.peek() == Some(&'"') => {
+                    characters.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => {
+                    if character == '\n' {
+                        line += 1;
+                    }
+                    field.push(character);
+                }
+            }
+            continue;
+        }
+        match character {
+            '"' if field.is_empty() && quoted.is_none() => {
+                quoted = Some(line);
+                in_quotes = true;
+            }
+            '"' => return Err(CsvError::MisplacedQuote { line }),
+            ',' => {
+                row.push(core::mem::take(&mut field));
+                quoted = None;
+            }
+            '\r' if characters.peek() == Some(&'\n') => {}
+            '\n' => {
+                if !row.is_empty() || !field.is_empty() || quoted.is_some() {
+                    row.push(core::mem::take(&mut field));
+                    rows.push(core::mem::take(&mut row));
+                }
+                quoted = None;
+                line += 1;
+            }
+            _ if quoted.is_some() => return Err(CsvError::MisplacedQuote { line }),
+            _ => field.push(character),
+        }
+    }
+    if in_quotes {
+        return Err(CsvError::UnterminatedQuote {
+            line: quoted.unwrap(),
+        });
+    }
+    if !row.is_empty() || !field.is_empty() || quoted.is_some() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+//Quotes only the fields which need it. Every row ends with a newline.
+pub fn write_csv(rows: &[Vec<String>]) -> String {
+    let mut output = String::new();
+    for row in rows {
+        for (index, field) in row.iter().enumerate() {
+            if index != 0 {
+                output.push(',');
+            }
+            if field.contains([',', '"', '\n', '\r']) {
+                output.push('"');
+                output.push_str(&field.replace('"', "\"\""));
+                output.push('"');
+            } else {
+                output.push_str(field);
+            }
+        }
+        output.push('\n');
+    }
+    output
+}
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+//Models of physical systems to close the loop around a control graph. They only exist in the
+//simulator and have no node type.
+use super::*;
+pub trait Plant: fmt::Debug {
+    //Advances the plant by dt seconds with the control input held at control and returns the new
+    //sensor reading.
+    fn update(&mut self, control: f64, dt: f64) -> f64;
+}
+//Approaches gain * control exponentially with the given time constant in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FirstOrderLag {
+    pub gain: f64,
+    pub time_constant: f64,
+    pub output: f64,
+}
+impl FirstOrderLag {
+    pub fn new(gain: f64, time_constant: f64) -> Self {
+        Self {
+            gain,
+            time_constant,
+            output: 0.0,
+        }
+    }
+}
+impl Plant for FirstOrderLag {
+    fn update(&mut self, control: f64, dt: f64) -> f64 {
+        //This is exact for a control input held over the whole step.
+        let target = self.gain * control;
+        self.output = target + (self.output - target) * (-dt / self.time_constant).exp();
+        self.output
+    }
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Measurement {
+    Position,
+    Velocity,
+}
+//A brushed DC motor driving an inertia with viscous friction. The control input is the voltage
+//and positions are in radians.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DcMotor {
+    pub resistance: f64,
+    pub torque_constant: f64,
+    pub back_emf_constant: f64,
+    pub inertia: f64,
+    pub friction: f64,
+    pub position: f64,
+    pub velocity: f64,
+    pub measurement: Measurement,
+}
+impl DcMotor {
+    pub fn new(
+        resistance: f64,
+        torque_constant: f64,
+        back_emf_constant: f64,
+        inertia: f64,
+        friction: f64,
+        measurement: Measurement,
+    ) -> Self {
+        Self {
+            resistance,
+            torque_constant,
+            back_emf_constant,
+            inertia,
+            friction,
+            position: 0.0,
+            velocity: 0.0,
+            measurement,
+        }
+    }
+}
+impl Plant for DcMotor {
+    fn update(&mut self, voltage: f64, dt: f64) -> f64 {
+        //The motor current makes the velocity first order:
+        //  dv/dt = drive - damping * v
+        //which is solved exactly for the step.
+        let drive = self.torque_constant * voltage / (self.resistance * self.inertia);
+        let damping = (self.torque_constant * self.back_emf_constant / self.resistance
+            + self.friction)
+            / self.inertia;
+        if damping == 0.0 {
+            self.position += self.velocity * dt + drive * dt * dt / 2.0;
+            self.velocity += drive * dt;
+        } else {
+            let steady = drive / damping;
+            let decay = (-damping * dt).exp();
+            self.position += steady * dt + (self.velocity - steady) * (1.0 - decay) / damping;
+            self.velocity = steady + (self.velocity - steady) * decay;
+        }
+        match self.measurement {
+            Measurement::Position => self.position,
+            Measurement::Velocity => self.velocity,
+        }
+    }
+}
+//Integrates gain * control with the control input clamped to plus or minus control_limit. The
+//sensor reading has uniform noise of up to plus or minus noise added.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Integrator {
+    pub gain: f64,
+    pub control_limit: f64,
+    pub noise: f64,
+    pub output: f64,
+    //State of the xorshift64 generator for the noise. This must not be 0.
+    pub seed: u64,
+}
+impl Integrator {
+    pub fn new(gain: f64, control_limit: f64, noise: f64, seed: u64) -> Self {
+        Self {
+            gain,
+            control_limit,
+            noise,
+            output: 0.0,
+            seed: seed.max(1),
+        }
+    }
+    //Uniform in [-1, 1)
+    fn random(&mut self) -> f64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        (self.seed >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+}
+impl Plant for Integrator {
+    fn update(&mut self, control: f64, dt: f64) -> f64 {
+        let control = control.clamp(-self.control_limit, self.control_limit);
+        self.output += self.gain * control * dt;
+        self.output + self.noise * self.random()
+    }
+}
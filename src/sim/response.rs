@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+//Measures of how a system responds to a step in its setpoint
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepResponse {
+    //Nanoseconds to go from 10% to 90% of the way to the target, or None if it never got there
+    pub rise_time: Option<i64>,
+    //How far past the target it went as a fraction of the step, or 0 if it never did
+    pub overshoot: f64,
+    //Nanoseconds from the first sample until it stayed within 2% of the step from the target, or
+    //None if it was outside of that at the last sample
+    pub settling_time: Option<i64>,
+    //The target minus the last sample
+    pub steady_state_error: f64,
+}
+//Samples are (time in nanoseconds, value) pairs in order. initial is the value before the step.
+//Returns None if there are no samples or there is no step because initial and target are equal.
+pub fn step_response(samples: &[(i64, f64)], initial: f64, target: f64) -> Option<StepResponse> {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return None;
+    };
+    let start = first.0;
+    let step = target - initial;
+    if step == 0.0 || step.is_nan() {
+        return None;
+    }
+    //How far along the step each sample is, with 1 at the target
+    let progress = |value: f64| (value - initial) / step;
+    let crossing = |fraction: f64| {
+        samples
+            .iter()
+            .find(|(_, value)| progress(*value) >= fraction)
+            .map(|(time, _)| *time)
+    };
+    let rise_time = match (crossing(0.1), crossing(0.9)) {
+        (Some(low), Some(high)) => Some(high - low),
+        _ => None,
+    };
+    let overshoot = samples
+        .iter()
+        .map(|(_, value)| progress(*value) - 1.0)
+        .fold(0.0, f64::max);
+    let outside = samples
+        .iter()
+        .rposition(|(_, value)| (progress(*value) - 1.0).abs() > 0.02);
+    let settling_time = match outside {
+        None => Some(0),
+        Some(last) if last + 1 < samples.len() => Some(samples[last + 1].0 - start),
+        Some(_) => None,
+    };
+    Some(StepResponse {
+        rise_time,
+        overshoot,
+        settling_time,
+        steady_state_error: target - last.1,
+    })
+}
@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+//Replaying recorded inputs through a graph and recording its outputs. In CSV, the first column is
+//the time in nanoseconds and is headed time. The other columns are headed with the label of a
+//node as written by to_text, so n3 is node 3. Every value in a column has the same type, which is
+//the value type of the node if it has one and otherwise a float.
+use super::*;
+//Recorded values for a source node, in nanoseconds
+pub type Samples = Vec<(i64, Value)>;
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Trace {
+    pub(super) node: usize,
+    pub(super) samples: Samples,
+    pub(super) next: usize,
+}
+impl Simulator {
+    //From the first step at or after each sample's time, the node outputs that sample with its
+    //own time as the timestamp. The node is not evaluated while it has a trace.
+    pub fn bind_trace(&mut self, node: usize, mut samples: Samples) -> Result<(), NodeNotFound> {
+        self.check_node(node)?;
+        samples.sort_by_key(|(time, _)| *time);
+        self.external[node] = true;
+        self.traces.push(Trace {
+            node,
+            samples,
+            next: 0,
+        });
+        Ok(())
+    }
+}
+//Keeps the outputs of some nodes at every step to write them as CSV.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recorder {
+    nodes: Vec<usize>,
+    rows: Vec<(i64, Vec<Output>)>,
+}
+impl Recorder {
+    pub fn new(nodes: Vec<usize>) -> Self {
+        Self {
+            nodes,
+            rows: Vec::new(),
+        }
+    }
+    pub fn record(&mut self, simulator: &Simulator) {
+        let outputs = self
+            .nodes
+            .iter()
+            .map(|node| simulator.output(*node))
+            .collect();
+        self.rows.push((simulator.time(), outputs));
+    }
+    //None is an empty cell and errors are written like Err(FromNone).
+    pub fn to_csv(&self) -> String {
+        let mut rows = Vec::with_capacity(self.rows.len() + 1);
+        let mut header = vec![String::from("time")];
+        header.extend(self.nodes.iter().map(|node| format!("n{}", node)));
+        rows.push(header);
+        for (time, outputs) in &self.rows {
+            let mut row = vec![time.to_string()];
+            row.extend(outputs.iter().map(|output| match output {
+                Ok(Some(datum)) => format_value(datum.value),
+                Ok(None) => String::new(),
+                Err(error) => format!("Err({:?})", error),
+            }));
+            rows.push(row);
+        }
+        write_csv(&rows)
+    }
+}
+fn format_value(value: Value) -> String {
+    match value {
+        Value::Float(value) => format!("{:?}", value),
+        Value::Integer(value) => value.to_string(),
+        Value::Bool(value) => value.to_string(),
+        Value::State(state) => format!(
+            "({:?} {:?} {:?})",
+            state.position, state.velocity, state.acceleration
+        ),
+    }
+}
+fn parse_value(cell: &str, value_type: Option<ValueType>) -> Option<Value> {
+    match value_type {
+        Some(ValueType::Bool) => match cell {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Some(ValueType::I64) => cell.parse().ok().map(Value::Integer),
+        _ => cell.parse().ok().map(Value::Float),
+    }
+}
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimulateError {
+    ParseFile(error::ParseFile),
+    Simulator(SimulatorError),
+    Csv(CsvError),
+    NodeNotFound(NodeNotFound),
+    Step(SimError),
+    MissingTimeColumn,
+    UnknownColumn { column: usize },
+    //A node has more than one column.
+    DuplicateColumn { column: usize },
+    //Rows start at 0 with the header.
+    InvalidTime { row: usize },
+    InvalidValue { row: usize, column: usize },
+}
+impl fmt::Display for SimulateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ParseFile(_) => write!(f, "failed to parse file"),
+            Self::Simulator(_) => write!(f, "failed to set up simulation"),
+            Self::Csv(_) => write!(f, "failed to parse inputs"),
+            Self::NodeNotFound(_) => write!(f, "node to record or replay is not in the graph"),
+            Self::Step(_) => write!(f, "failed to step simulation"),
+            Self::MissingTimeColumn => write!(f, "first column of inputs is not headed time"),
+            Self::UnknownColumn { column } => {
+                write!(
+                    f,
+                    "column {} of inputs is not headed with a node label",
+                    column
+                )
+            }
+            Self::DuplicateColumn { column } => {
+                write!(
+                    f,
+                    "column {} of inputs is for a node already in inputs",
+                    column
+                )
+            }
+            Self::InvalidTime { row } => write!(f, "time in row {} of inputs is invalid", row),
+            Self::InvalidValue { row, column } => write!(
+                f,
+                "value in row {} column {} of inputs is invalid",
+                row, column
+            ),
+        }
+    }
+}
+impl core::error::Error for SimulateError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::ParseFile(error) => Some(error),
+            Self::Simulator(error) => Some(error),
+            Self::Csv(error) => Some(error),
+            Self::NodeNotFound(error) => Some(error),
+            Self::Step(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+//Reads traces for source nodes from CSV. Empty cells are skipped so that columns can have
+//samples at different times.
+pub fn read_traces(inputs: &str, nodes: &[Node]) -> Result<Vec<(usize, Samples)>, SimulateError> {
+    let rows = read_csv(inputs).map_err(SimulateError::Csv)?;
+    let Some((header, rows)) = rows.split_first() else {
+        return Err(SimulateError::MissingTimeColumn);
+    };
+    if header.first().map(String::as_str) != Some("time") {
+        return Err(SimulateError::MissingTimeColumn);
+    }
+    let mut traces = Vec::with_capacity(header.len() - 1);
+    for (column, label) in header.iter().enumerate().skip(1) {
+        match label
+            .strip_prefix('n')
+            .and_then(|index| index.parse::<usize>().ok())
+        {
+            Some(node) if traces.iter().any(|(traced, _)| *traced == node) => {
+                return Err(SimulateError::DuplicateColumn { column });
+            }
+            Some(node) if node < nodes.len() => traces.push((node, Vec::new())),
+            _ => return Err(SimulateError::UnknownColumn { column }),
+        }
+    }
+    for (row_index, row) in rows.iter().enumerate() {
+        let row_index = row_index + 1;
+        let time = row
+            .first()
+            .and_then(|time| time.parse::<i64>().ok())
+            .ok_or(SimulateError::InvalidTime { row: row_index })?;
+        for (column, cell) in row.iter().enumerate().skip(1) {
+            if cell.is_empty() {
+                continue;
+            }
+            let Some((node, samples)) = traces.get_mut(column - 1) else {
+                return Err(SimulateError::UnknownColumn { column });
+            };
+            let value =
+                parse_value(cell, nodes[*node].value_type).ok_or(SimulateError::InvalidValue {
+                    row: row_index,
+                    column,
+                })?;
+            samples.push((time, value));
+        }
+    }
+    Ok(traces)
+}
+//Reads a file, replays the traces in inputs through it and returns the outputs of the nodes in
+//record at every step as CSV. It runs until the time of the last sample in inputs.
+pub fn simulate(
+    file: &[u8],
+    inputs: &str,
+    timestep: i64,
+    record: &[usize],
+) -> Result<String, SimulateError> {
+    let nodes =
+        read_file_with_policy(file, VersionPolicy::Strict).map_err(SimulateError::ParseFile)?;
+    if let Some(node) = record.iter().find(|node| **node >= nodes.len()) {
+        return Err(SimulateError::NodeNotFound(NodeNotFound { node: *node }));
+    }
+    let traces = read_traces(inputs, &nodes)?;
+    let end = traces
+        .iter()
+        .flat_map(|(_, samples)| samples.iter().map(|(time, _)| *time))
+        .max()
+        .unwrap_or(0);
+    let mut recorder = Recorder::new(record.to_vec());
+    let mut simulator = Simulator::new(nodes, timestep).map_err(SimulateError::Simulator)?;
+    for (node, samples) in traces {
+        simulator
+            .bind_trace(node, samples)
+            .map_err(SimulateError::NodeNotFound)?;
+    }
+    while simulator.time() < end {
+        simulator.step().map_err(SimulateError::Step)?;
+        recorder.record(&simulator);
+    }
+    Ok(recorder.to_csv())
+}
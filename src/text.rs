@@ -0,0 +1,448 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+//A text form of node graphs for reviewing and editing by hand. Each node is written as
+//  label = NodeType(inputs) [parameters] as ValueType @ (x, y);
+//where inputs are labels of other nodes. The input list, parameter list and value type can each
+//be left out. A node type which this crate does not know is written as its ID after a #, and so is
+//an input which refers to a node that does not exist. Known node types can be read this way too.
+//Parameters are floats like 1.0, integers like 1, true or false, and times in nanoseconds like
+//1000ns. A NaN other than the one NaN parses to is written with its bits like
+//NaN(0xfff8000000000000) so that its sign and payload are kept. Everything after // on a line is
+//a comment.
+use super::*;
+use std::collections::HashMap;
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TextErrorKind {
+    UnexpectedCharacter(char),
+    UnexpectedEnd,
+    Expected(&'static str),
+    UnknownNodeType(String),
+    InvalidNumber(String),
+    UnknownLabel(String),
+    DuplicateLabel(String),
+    TooManyNodes,
+}
+impl fmt::Display for TextErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedCharacter(character) => {
+                write!(f, "unexpected character {:?}", character)
+            }
+            Self::UnexpectedEnd => write!(f, "unexpected end of text"),
+            Self::Expected(expected) => write!(f, "expected {}", expected),
+            Self::UnknownNodeType(name) => write!(f, "unknown node type {}", name),
+            Self::InvalidNumber(number) => write!(f, "invalid number {}", number),
+            Self::UnknownLabel(label) => write!(f, "no node is labeled {}", label),
+            Self::DuplicateLabel(label) => write!(f, "more than one node is labeled {}", label),
+            Self::TooManyNodes => write!(f, "too many nodes for u16 input indices"),
+        }
+    }
+}
+//Lines and columns start at 1. Columns count characters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: TextErrorKind,
+}
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.kind
+        )
+    }
+}
+impl core::error::Error for TextError {}
+fn node_type_from_name(name: &str) -> Option<NodeType> {
+    (0..=u16::MAX)
+        .map_while(|id| NodeType::try_from(id).ok())
+        .find(|node_type| node_type.to_string() == name)
+}
+fn write_float(output: &mut String, value: f64) {
+    use fmt::Write;
+    if value.is_nan() && value.to_bits() != f64::NAN.to_bits() {
+        write!(output, "NaN({:#018x})", value.to_bits())
+    } else {
+        write!(output, "{:?}", value)
+    }
+    .unwrap();
+}
+fn write_parameter(output: &mut String, parameter: &Parameter) {
+    use fmt::Write;
+    match parameter {
+        Parameter::Float(value) => {
+            write_float(output, *value);
+            Ok(())
+        }
+        Parameter::Integer(value) => write!(output, "{}", value),
+        Parameter::Bool(value) => write!(output, "{}", value),
+        Parameter::Time(value) => write!(output, "{}ns", value),
+    }
+    .unwrap();
+}
+fn write_value_type(output: &mut String, value_type: ValueType) {
+    use fmt::Write;
+    match value_type {
+        ValueType::F32 => write!(output, "f32"),
+        ValueType::F64 => write!(output, "f64"),
+        ValueType::Bool => write!(output, "bool"),
+        ValueType::I64 => write!(output, "i64"),
+        ValueType::Quantity(dimension) => write!(
+            output,
+            "Quantity({}, {}, {})",
+            dimension.length, dimension.time, dimension.mass
+        ),
+    }
+    .unwrap();
+}
+//Writes one node per line with node i labeled ni. from_text followed by to_text gives back
+//exactly the same text for anything this returns.
+pub fn to_text(nodes: &[Node]) -> String {
+    use fmt::Write;
+    let mut output = String::new();
+    for (index, node) in nodes.iter().enumerate() {
+        write!(output, "n{} = ", index).unwrap();
+        match node.id {
+            Ok(node_type) => write!(output, "{}", node_type),
+            Err(id) => write!(output, "#{}", id),
+        }
+        .unwrap();
+        if !node.inputs.is_empty() {
+            output.push('(');
+            for (position, input) in node.inputs.iter().enumerate() {
+                if position != 0 {
+                    output.push_str(", ");
+                }
+                if (*input as usize) < nodes.len() {
+                    write!(output, "n{}", input).unwrap();
+                } else {
+                    write!(output, "#{}", input).unwrap();
+                }
+            }
+            output.push(')');
+        }
+        if let Some(parameters) = &node.parameters {
+            output.push_str(" [");
+            for (position, parameter) in parameters.iter().enumerate() {
+                if position != 0 {
+                    output.push_str(", ");
+                }
+                write_parameter(&mut output, parameter);
+            }
+            output.push(']');
+        }
+        if let Some(value_type) = node.value_type {
+            output.push_str(" as ");
+            write_value_type(&mut output, value_type);
+        }
+        output.push_str(" @ (");
+        write_float(&mut output, node.x);
+        output.push_str(", ");
+        write_float(&mut output, node.y);
+        output.push_str(");\n");
+    }
+    output
+}
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum TextTokenKind {
+    //Labels, node type names, numbers and keywords
+    Word(String),
+    Punctuation(char),
+}
+#[derive(Clone, Debug)]
+struct TextToken {
+    kind: TextTokenKind,
+    line: usize,
+    column: usize,
+}
+fn is_word_character(character: char) -> bool {
+    character.is_alphanumeric() || matches!(character, '_' | '.' | '+' | '-')
+}
+fn tokenize(text: &str) -> Result<Vec<TextToken>, TextError> {
+    let mut tokens = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let characters = line.chars().collect::<Vec<_>>();
+        let mut position = 0;
+        while position < characters.len() {
+            let character = characters[position];
+            let start = position;
+            position += 1;
+            let kind = match character {
+                '/' if characters.get(position) == Some(&'/') => break,
+                '=' | '(' | ')' | '[' | ']' | ',' | ';' | '@' | '#' => {
+                    TextTokenKind::Punctuation(character)
+                }
+                _ if character.is_whitespace() => continue,
+                _ if is_word_character(character) => {
+                    while position < characters.len() && is_word_character(characters[position]) {
+                        position += 1;
+                    }
+                    TextTokenKind::Word(characters[start..position].iter().collect())
+                }
+                _ => {
+                    return Err(TextError {
+                        line: line_index + 1,
+                        column: start + 1,
+                        kind: TextErrorKind::UnexpectedCharacter(character),
+                    });
+                }
+            };
+            tokens.push(TextToken {
+                kind,
+                line: line_index + 1,
+                column: start + 1,
+            });
+        }
+    }
+    Ok(tokens)
+}
+enum Input {
+    Label(String, TextToken),
+    //Written with a #, for inputs which refer to nodes that do not exist
+    Index(u16),
+}
+struct TextParser {
+    tokens: Vec<TextToken>,
+    position: usize,
+    //Where the text ends, for errors about things missing at the end
+    end: (usize, usize),
+}
+impl TextParser {
+    fn error_at(token: &TextToken, kind: TextErrorKind) -> TextError {
+        TextError {
+            line: token.line,
+            column: token.column,
+            kind,
+        }
+    }
+    fn next(&mut self) -> Result<&TextToken, TextError> {
+        match self.tokens.get(self.position) {
+            Some(token) => {
+                self.position += 1;
+                Ok(token)
+            }
+            None => Err(TextError {
+                line: self.end.0,
+                column: self.end.1,
+                kind: TextErrorKind::UnexpectedEnd,
+            }),
+        }
+    }
+    fn peek_is(&self, punctuation: char) -> bool {
+        matches!(
+            self.tokens.get(self.position),
+            Some(TextToken { kind: TextTokenKind::Punctuation(found), .. }) if *found == punctuation
+        )
+    }
+    fn punctuation(&mut self, punctuation: char, expected: &'static str) -> Result<(), TextError> {
+        let token = self.next()?;
+        match token.kind {
+            TextTokenKind::Punctuation(found) if found == punctuation => Ok(()),
+            _ => Err(Self::error_at(token, TextErrorKind::Expected(expected))),
+        }
+    }
+    fn word(&mut self, expected: &'static str) -> Result<(String, TextToken), TextError> {
+        let token = self.next()?;
+        match &token.kind {
+            TextTokenKind::Word(word) => Ok((word.clone(), token.clone())),
+            _ => Err(Self::error_at(token, TextErrorKind::Expected(expected))),
+        }
+    }
+    fn number<T: core::str::FromStr>(&mut self, expected: &'static str) -> Result<T, TextError> {
+        let (word, token) = self.word(expected)?;
+        word.parse()
+            .map_err(|_| Self::error_at(&token, TextErrorKind::InvalidNumber(word)))
+    }
+    //Parses a comma separated list up to the closing character, which is consumed.
+    fn list<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T, TextError>,
+    ) -> Result<Vec<T>, TextError> {
+        let mut output = Vec::new();
+        loop {
+            if self.peek_is(close) {
+                self.next()?;
+                return Ok(output);
+            }
+            output.push(item(self)?);
+            if !self.peek_is(close) {
+                self.punctuation(',', "a comma")?;
+            }
+        }
+    }
+    //Reads a float whose first word has already been read
+    fn float_from(&mut self, word: String, token: TextToken) -> Result<f64, TextError> {
+        let invalid = |word| Self::error_at(&token, TextErrorKind::InvalidNumber(word));
+        if word != "NaN" || !self.peek_is('(') {
+            return word.parse().map_err(|_| invalid(word));
+        }
+        self.next()?;
+        let (bits, bits_token) = self.word("the bits of a NaN")?;
+        let value = bits
+            .strip_prefix("0x")
+            .and_then(|digits| u64::from_str_radix(digits, 16).ok())
+            .map(f64::from_bits)
+            .filter(|value| value.is_nan())
+            .ok_or_else(|| Self::error_at(&bits_token, TextErrorKind::InvalidNumber(bits)))?;
+        self.punctuation(')', "a closing parenthesis")?;
+        Ok(value)
+    }
+    fn float(&mut self, expected: &'static str) -> Result<f64, TextError> {
+        let (word, token) = self.word(expected)?;
+        self.float_from(word, token)
+    }
+    fn input(&mut self) -> Result<Input, TextError> {
+        if self.peek_is('#') {
+            self.next()?;
+            return Ok(Input::Index(self.number("a node index")?));
+        }
+        let (label, token) = self.word("a label")?;
+        Ok(Input::Label(label, token))
+    }
+    //An ID after a # is the same as the name of its node type if it has one.
+    fn node_type(&mut self) -> Result<Result<NodeType, u16>, TextError> {
+        if self.peek_is('#') {
+            self.next()?;
+            return Ok(NodeType::try_from(self.number::<u16>("a node ID")?));
+        }
+        let (word, token) = self.word("a node type")?;
+        match node_type_from_name(&word) {
+            Some(node_type) => Ok(Ok(node_type)),
+            None => Err(Self::error_at(&token, TextErrorKind::UnknownNodeType(word))),
+        }
+    }
+    fn parameter(&mut self) -> Result<Parameter, TextError> {
+        let (word, token) = self.word("a parameter")?;
+        let invalid = || Self::error_at(&token, TextErrorKind::InvalidNumber(word.clone()));
+        Ok(match word.as_str() {
+            "true" => Parameter::Bool(true),
+            "false" => Parameter::Bool(false),
+            _ if word.ends_with("ns") => {
+                Parameter::Time(word[..word.len() - 2].parse().map_err(|_| invalid())?)
+            }
+            _ if word
+                .trim_start_matches(['+', '-'])
+                .chars()
+                .all(|character| character.is_ascii_digit()) =>
+            {
+                Parameter::Integer(word.parse().map_err(|_| invalid())?)
+            }
+            _ => Parameter::Float(self.float_from(word, token)?),
+        })
+    }
+    fn value_type(&mut self) -> Result<ValueType, TextError> {
+        let (word, token) = self.word("a value type")?;
+        Ok(match word.as_str() {
+            "f32" => ValueType::F32,
+            "f64" => ValueType::F64,
+            "bool" => ValueType::Bool,
+            "i64" => ValueType::I64,
+            "Quantity" => {
+                self.punctuation('(', "an opening parenthesis")?;
+                let length = self.number("an exponent")?;
+                self.punctuation(',', "a comma")?;
+                let time = self.number("an exponent")?;
+                self.punctuation(',', "a comma")?;
+                let mass = self.number("an exponent")?;
+                self.punctuation(')', "a closing parenthesis")?;
+                ValueType::Quantity(Dimension::new(length, time, mass))
+            }
+            _ => {
+                return Err(Self::error_at(
+                    &token,
+                    TextErrorKind::Expected("a value type"),
+                ));
+            }
+        })
+    }
+}
+//Reads text in the form written by to_text. Labels can be any words and inputs can refer to
+//nodes later in the text. Nodes are numbered in the order they are written.
+pub fn from_text(text: &str) -> Result<Vec<Node>, TextError> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let end = match lines.last() {
+        Some(line) => (lines.len(), line.chars().count() + 1),
+        None => (1, 1),
+    };
+    let mut parser = TextParser {
+        tokens: tokenize(text)?,
+        position: 0,
+        end,
+    };
+    let mut labels = HashMap::new();
+    //Each node with its input labels
+    let mut nodes = Vec::new();
+    while parser.position < parser.tokens.len() {
+        let (label, label_token) = parser.word("a label")?;
+        if nodes.len() > u16::MAX as usize {
+            return Err(TextParser::error_at(
+                &label_token,
+                TextErrorKind::TooManyNodes,
+            ));
+        }
+        if labels.insert(label.clone(), nodes.len() as u16).is_some() {
+            return Err(TextParser::error_at(
+                &label_token,
+                TextErrorKind::DuplicateLabel(label),
+            ));
+        }
+        parser.punctuation('=', "an equals sign")?;
+        let id = parser.node_type()?;
+        let mut inputs = Vec::new();
+        if parser.peek_is('(') {
+            parser.next()?;
+            inputs = parser.list(')', TextParser::input)?;
+        }
+        let mut node = Node::new(id, 0.0, 0.0, Vec::new());
+        if parser.peek_is('[') {
+            parser.next()?;
+            node = node.with_parameters(parser.list(']', TextParser::parameter)?);
+        }
+        if !parser.peek_is('@') {
+            let (word, token) = parser.word("an @")?;
+            if word != "as" {
+                return Err(TextParser::error_at(
+                    &token,
+                    TextErrorKind::Expected("an @"),
+                ));
+            }
+            node = node.with_value_type(parser.value_type()?);
+        }
+        parser.punctuation('@', "an @")?;
+        parser.punctuation('(', "an opening parenthesis")?;
+        node.x = parser.float("an x coordinate")?;
+        parser.punctuation(',', "a comma")?;
+        node.y = parser.float("a y coordinate")?;
+        parser.punctuation(')', "a closing parenthesis")?;
+        parser.punctuation(';', "a semicolon")?;
+        nodes.push((node, inputs));
+    }
+    nodes
+        .into_iter()
+        .map(|(mut node, inputs)| {
+            for input in inputs {
+                let (label, token) = match input {
+                    Input::Label(label, token) => (label, token),
+                    Input::Index(index) => {
+                        node.inputs.push(index);
+                        continue;
+                    }
+                };
+                match labels.get(&label) {
+                    Some(index) => node.inputs.push(*index),
+                    None => {
+                        return Err(TextParser::error_at(
+                            &token,
+                            TextErrorKind::UnknownLabel(label),
+                        ));
+                    }
+                }
+            }
+            Ok(node)
+        })
+        .collect()
+}
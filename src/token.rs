@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+use super::*;
+use core::ops::Range;
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(i8)]
+pub enum Tag {
+    Skip1 = tags::SKIP_1,
+    Skip2 = tags::SKIP_2,
+    Skip4 = tags::SKIP_4,
+    Skip8 = tags::SKIP_8,
+    Skip16 = tags::SKIP_16,
+    SkipU8 = tags::SKIP_U8,
+    SkipU16 = tags::SKIP_U16,
+    NodeID = tags::NODE_ID,
+    Coordinates = tags::COORDINATES,
+    NodeSectionStart = tags::NODE_SECTION_START,
+    NodeSectionEnd = tags::NODE_SECTION_END,
+    NodeStart = tags::NODE_START,
+    NodeEnd = tags::NODE_END,
+    NodeInputListStart = tags::NODE_INPUT_LIST_START,
+    NodeInputListEnd = tags::NODE_INPUT_LIST_END,
+    ParameterListStart = tags::PARAMETER_LIST_START,
+    ParameterListEnd = tags::PARAMETER_LIST_END,
+    ParameterFloat = tags::PARAMETER_FLOAT,
+    ParameterInteger = tags::PARAMETER_INTEGER,
+    ParameterBool = tags::PARAMETER_BOOL,
+    ParameterTime = tags::PARAMETER_TIME,
+    ValueType = tags::VALUE_TYPE,
+}
+impl TryFrom<i8> for Tag {
+    type Error = i8;
+    fn try_from(was: i8) -> Result<Self, i8> {
+        Ok(match was {
+            tags::SKIP_1 => Self::Skip1,
+            tags::SKIP_2 => Self::Skip2,
+            tags::SKIP_4 => Self::Skip4,
+            tags::SKIP_8 => Self::Skip8,
+            tags::SKIP_16 => Self::Skip16,
+            tags::SKIP_U8 => Self::SkipU8,
+            tags::SKIP_U16 => Self::SkipU16,
+            tags::NODE_ID => Self::NodeID,
+            tags::COORDINATES => Self::Coordinates,
+            tags::NODE_SECTION_START => Self::NodeSectionStart,
+            tags::NODE_SECTION_END => Self::NodeSectionEnd,
+            tags::NODE_START => Self::NodeStart,
+            tags::NODE_END => Self::NodeEnd,
+            tags::NODE_INPUT_LIST_START => Self::NodeInputListStart,
+            tags::NODE_INPUT_LIST_END => Self::NodeInputListEnd,
+            tags::PARAMETER_LIST_START => Self::ParameterListStart,
+            tags::PARAMETER_LIST_END => Self::ParameterListEnd,
+            tags::PARAMETER_FLOAT => Self::ParameterFloat,
+            tags::PARAMETER_INTEGER => Self::ParameterInteger,
+            tags::PARAMETER_BOOL => Self::ParameterBool,
+            tags::PARAMETER_TIME => Self::ParameterTime,
+            tags::VALUE_TYPE => Self::ValueType,
+            _ => return Err(was),
+        })
+    }
+}
+pub fn tag_result_to_i8(was: Result<Tag, i8>) -> i8 {
+    match was {
+        Ok(tag) => tag as i8,
+        Err(error) => error,
+    }
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TokenKind {
+    Tag(Result<Tag, i8>),
+    Skip(Tag), //A skip tag along with its length bytes if it has any
+    Numbers,   //A run of bytes covered by a skip tag. This can be parts of numbers as well.
+}
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub bytes: &'a [u8],
+    pub range: Range<usize>,
+}
+#[derive(Clone, Debug)]
+pub struct Tokenizer<'a> {
+    data: &'a [u8],
+    position: usize,
+    skip_next: usize,
+    skip_start: usize,
+    skip_cut: bool,
+}
+impl<'a> Tokenizer<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            skip_next: 0,
+            skip_start: 0,
+            skip_cut: false,
+        }
+    }
+    //If the data ended before everything a skip tag covers, this returns the offset of the skip
+    //tag. It only returns Some once the tokenizer has reached the end of the data.
+    pub fn truncated_skip(&self) -> Option<usize> {
+        if self.position >= self.data.len() && (self.skip_next >= 1 || self.skip_cut) {
+            return Some(self.skip_start);
+        }
+        None
+    }
+    fn token(&mut self, kind: TokenKind, end: usize) -> Token<'a> {
+        let range = self.position..end.min(self.data.len());
+        self.position = range.end;
+        Token {
+            kind,
+            bytes: &self.data[range.clone()],
+            range,
+        }
+    }
+}
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token<'a>;
+    fn next(&mut self) -> Option<Token<'a>> {
+        let start = self.position;
+        let byte = *self.data.get(start)?;
+        if self.skip_next >= 1 {
+            let token = self.token(TokenKind::Numbers, start + self.skip_next);
+            self.skip_next -= token.bytes.len();
+            return Some(token);
+        }
+        let (tag, length_bytes) = match byte {
+            tags_u8::SKIP_1 => (Tag::Skip1, 0),
+            tags_u8::SKIP_2 => (Tag::Skip2, 0),
+            tags_u8::SKIP_4 => (Tag::Skip4, 0),
+            tags_u8::SKIP_8 => (Tag::Skip8, 0),
+            tags_u8::SKIP_16 => (Tag::Skip16, 0),
+            tags_u8::SKIP_U8 => (Tag::SkipU8, 1),
+            tags_u8::SKIP_U16 => (Tag::SkipU16, 2),
+            _ => {
+                let tag = Tag::try_from(byte as i8);
+                return Some(self.token(TokenKind::Tag(tag), start + 1));
+            }
+        };
+        let token = self.token(TokenKind::Skip(tag), start + 1 + length_bytes);
+        self.skip_start = start;
+        self.skip_cut = token.bytes.len() < 1 + length_bytes;
+        self.skip_next = match tag {
+            Tag::Skip1 => 1,
+            Tag::Skip2 => 2,
+            Tag::Skip4 => 4,
+            Tag::Skip8 => 8,
+            Tag::Skip16 => 16,
+            //If the length bytes are cut off, there is nothing left to skip anyway.
+            Tag::SkipU8 if token.bytes.len() == 2 => token.bytes[1] as usize + 1,
+            Tag::SkipU16 if token.bytes.len() == 3 => bytes_to_u16(&token.bytes[1..]) as usize + 1,
+            _ => 0,
+        };
+        Some(token)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn tokenizer_tags() {
+        let data = vec![
+            1u8,
+            100,
+            2,
+            3,
+            101,
+            tags_u8::SKIP_1,
+            100,
+            tags_u8::SKIP_8,
+            100,
+            1,
+            2,
+            3,
+            4,
+            5,
+            6,
+            101,
+            //SKIP_8 tag ends here
+            4,
+            100,
+            5,
+            6,
+            7,
+            101,
+            8,
+            tags_u8::SKIP_U8,
+            2,
+            100,
+            4,
+            101,
+            //SKIP_U8 tag ends here
+            100,
+            8,
+            9,
+            101,
+            5,
+            tags_u8::SKIP_U16,
+            //Just a really hacky way of inserting a u16 into an array of u8s.
+            u16_to_bytes(3)[0],
+            u16_to_bytes(3)[1],
+            100,
+            2,
+            101,
+            3,
+            //SKIP_U16 tag ends here
+            100,
+            10,
+            11,
+            101,
+            6,
+        ];
+        let mut found = Vec::new();
+        let mut current: Option<Vec<u8>> = None;
+        for token in Tokenizer::new(&data) {
+            match token.kind {
+                TokenKind::Tag(Err(100)) => current = Some(Vec::new()),
+                TokenKind::Tag(Err(101)) => found.extend(current.take()),
+                TokenKind::Tag(tag) => {
+                    if let Some(current) = &mut current {
+                        current.push(tag_result_to_i8(tag) as u8);
+                    }
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(
+            found,
+            vec![vec![2, 3], vec![5, 6, 7], vec![8, 9], vec![10, 11]]
+        );
+    }
+    #[test]
+    fn tokenizer_ranges() {
+        let data = [
+            100u8,
+            tags_u8::SKIP_2,
+            1,
+            2,
+            tags_u8::SKIP_U8,
+            2,
+            3,
+            4,
+            5,
+            101,
+        ];
+        let tokens = Tokenizer::new(&data)
+            .map(|token| (token.kind, token.bytes, token.range))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Tag(Err(100)), &data[0..1], 0..1),
+                (TokenKind::Skip(Tag::Skip2), &data[1..2], 1..2),
+                (TokenKind::Numbers, &[1, 2][..], 2..4),
+                (TokenKind::Skip(Tag::SkipU8), &data[4..6], 4..6),
+                (TokenKind::Numbers, &[3, 4, 5][..], 6..9),
+                (TokenKind::Tag(Err(101)), &data[9..10], 9..10),
+            ]
+        );
+    }
+    #[test]
+    fn tokenizer_truncated_skip() {
+        let data = [1u8, tags_u8::SKIP_4, 1, 2];
+        let mut tokenizer = Tokenizer::new(&data);
+        assert_eq!(tokenizer.by_ref().count(), 3);
+        assert_eq!(tokenizer.truncated_skip(), Some(1));
+        let data = [1u8, 2, tags_u8::SKIP_U16, 1];
+        let mut tokenizer = Tokenizer::new(&data);
+        assert_eq!(tokenizer.by_ref().count(), 3);
+        assert_eq!(tokenizer.truncated_skip(), Some(2));
+        let data = [1u8, tags_u8::SKIP_2, 1, 2];
+        let mut tokenizer = Tokenizer::new(&data);
+        assert_eq!(tokenizer.truncated_skip(), None);
+        assert_eq!(tokenizer.by_ref().count(), 3);
+        assert_eq!(tokenizer.truncated_skip(), None);
+    }
+    #[test]
+    fn tag_round_trip() {
+        for byte in i8::MIN..=i8::MAX {
+            assert_eq!(tag_result_to_i8(Tag::try_from(byte)), byte);
+        }
+        assert_eq!(Tag::try_from(tags::NODE_END), Ok(Tag::NodeEnd));
+    }
+}
@@ -0,0 +1,249 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+use super::*;
+//The type of the values flowing out of a node. Quantity dimensions are not tracked here.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StreamType {
+    F32,
+    F64,
+    Bool,
+    I64,
+    Quantity,
+    State,
+    Command,
+}
+impl StreamType {
+    pub fn is_number(self) -> bool {
+        matches!(self, Self::F32 | Self::F64 | Self::I64 | Self::Quantity)
+    }
+}
+impl From<ValueType> for StreamType {
+    fn from(was: ValueType) -> Self {
+        match was {
+            ValueType::F32 => Self::F32,
+            ValueType::F64 => Self::F64,
+            ValueType::Bool => Self::Bool,
+            ValueType::I64 => Self::I64,
+            ValueType::Quantity(_) => Self::Quantity,
+        }
+    }
+}
+impl fmt::Display for StreamType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::F32 => write!(f, "f32"),
+            Self::F64 => write!(f, "f64"),
+            Self::Bool => write!(f, "bool"),
+            Self::I64 => write!(f, "i64"),
+            Self::Quantity => write!(f, "Quantity"),
+            Self::State => write!(f, "State"),
+            Self::Command => write!(f, "Command"),
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Expected {
+    Type(StreamType),
+    Number,
+}
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Type(stream_type) => write!(f, "{}", stream_type),
+            Self::Number => write!(f, "a number"),
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TypeMismatch {
+    pub node: usize,
+    pub input: usize,
+    pub expected: Expected,
+    pub found: StreamType,
+}
+impl fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "input {} of node {} is {} but should be {}",
+            self.input, self.node, self.found, self.expected
+        )
+    }
+}
+impl core::error::Error for TypeMismatch {}
+#[derive(Clone, Copy)]
+enum Slot {
+    Fixed(StreamType),
+    //The type the node is generic over
+    Generic,
+}
+#[derive(Clone, Copy)]
+enum Inputs {
+    Fixed(&'static [Slot]),
+    Variadic(Slot),
+}
+impl Inputs {
+    //Inputs past the end of a fixed list are left to validation.
+    fn get(self, index: usize) -> Option<Slot> {
+        match self {
+            Self::Fixed(slots) => slots.get(index).copied(),
+            Self::Variadic(slot) => Some(slot),
+        }
+    }
+}
+struct Signature {
+    inputs: Inputs,
+    //None if the output is the generic type
+    output: Option<StreamType>,
+    //Whether the generic type has to be a number
+    number: bool,
+}
+const fn signature(inputs: Inputs, output: Option<StreamType>, number: bool) -> Signature {
+    Signature {
+        inputs,
+        output,
+        number,
+    }
+}
+fn node_signature(node_type: NodeType) -> Signature {
+    use Slot::{Fixed, Generic};
+    use StreamType::*;
+    match node_type {
+        NodeType::ConstantGetter | NodeType::NoneGetter => {
+            signature(Inputs::Fixed(&[]), None, false)
+        }
+        NodeType::Expirer | NodeType::NoneToError | NodeType::NoneToValue => {
+            signature(Inputs::Fixed(&[Generic]), None, false)
+        }
+        NodeType::Latest => signature(Inputs::Variadic(Generic), None, false),
+        NodeType::CommandPID => signature(Inputs::Fixed(&[Fixed(State)]), Some(Command), false),
+        NodeType::EWMAStream
+        | NodeType::MovingAverageStream
+        | NodeType::DerivativeStream
+        | NodeType::IntegralStream => signature(Inputs::Fixed(&[Generic]), None, true),
+        NodeType::PIDControllerStream => signature(Inputs::Fixed(&[Fixed(F32)]), Some(F32), false),
+        NodeType::PositionToState | NodeType::VelocityToState | NodeType::AccelerationToState => {
+            signature(Inputs::Fixed(&[Fixed(Quantity)]), Some(State), false)
+        }
+        NodeType::FloatToQuantity => signature(Inputs::Fixed(&[Fixed(F32)]), Some(Quantity), false),
+        NodeType::QuantityToFloat => signature(Inputs::Fixed(&[Fixed(Quantity)]), Some(F32), false),
+        NodeType::DimensionAdder | NodeType::DimensionRemover => {
+            signature(Inputs::Fixed(&[Fixed(Quantity)]), Some(Quantity), false)
+        }
+        NodeType::FreezeStream => signature(Inputs::Fixed(&[Generic, Fixed(Bool)]), None, false),
+        NodeType::IfStream => signature(Inputs::Fixed(&[Fixed(Bool), Generic]), None, false),
+        NodeType::IfElseStream => {
+            signature(Inputs::Fixed(&[Fixed(Bool), Generic, Generic]), None, false)
+        }
+        NodeType::AndStream | NodeType::OrStream => signature(
+            Inputs::Fixed(&[Fixed(Bool), Fixed(Bool)]),
+            Some(Bool),
+            false,
+        ),
+        NodeType::NotStream => signature(Inputs::Fixed(&[Fixed(Bool)]), Some(Bool), false),
+        NodeType::SumStream | NodeType::ProductStream => {
+            signature(Inputs::Variadic(Generic), None, true)
+        }
+        NodeType::Sum2
+        | NodeType::DifferenceStream
+        | NodeType::Product2
+        | NodeType::QuotientStream => signature(Inputs::Fixed(&[Generic, Generic]), None, true),
+        NodeType::ExponentStream => {
+            signature(Inputs::Fixed(&[Fixed(F32), Fixed(F32)]), Some(F32), false)
+        }
+    }
+}
+fn parameter_type(parameter: &Parameter) -> Option<StreamType> {
+    match parameter {
+        Parameter::Float(_) => Some(StreamType::F32),
+        Parameter::Integer(_) => Some(StreamType::I64),
+        Parameter::Bool(_) => Some(StreamType::Bool),
+        Parameter::Time(_) => None,
+    }
+}
+//The type a node is generic over. A value type annotation wins, then the first input whose type
+//is known, then the value parameter of a ConstantGetter.
+fn generic_type(node: &Node, outputs: &[Option<StreamType>]) -> Option<StreamType> {
+    if let Some(value_type) = node.value_type {
+        return Some(value_type.into());
+    }
+    let node_type = node.id.ok()?;
+    let inputs = node_signature(node_type).inputs;
+    for (index, input) in node.inputs.iter().enumerate() {
+        if let Some(Slot::Generic) = inputs.get(index)
+            && let Some(Some(stream_type)) = outputs.get(*input as usize)
+        {
+            return Some(*stream_type);
+        }
+    }
+    if node_type == NodeType::ConstantGetter {
+        return parameter_type(node.parameters.as_ref()?.first()?);
+    }
+    None
+}
+//Unknown node types and types which cannot be worked out are None.
+pub fn output_types(nodes: &[Node]) -> Vec<Option<StreamType>> {
+    let mut outputs = vec![None; nodes.len()];
+    //Every pass settles at least one more node or nothing changes, so this always finishes.
+    loop {
+        let mut changed = false;
+        for (index, node) in nodes.iter().enumerate() {
+            if outputs[index].is_some() {
+                continue;
+            }
+            let Ok(node_type) = node.id else {
+                continue;
+            };
+            let output = match node_signature(node_type).output {
+                Some(output) => Some(output),
+                None => generic_type(node, &outputs),
+            };
+            if output.is_some() {
+                outputs[index] = output;
+                changed = true;
+            }
+        }
+        if !changed {
+            return outputs;
+        }
+    }
+}
+//Returns the output type of each node, or every input whose type does not match what its node
+//expects. Inputs whose types are not known are not checked.
+pub fn typecheck(nodes: &[Node]) -> Result<Vec<Option<StreamType>>, Vec<TypeMismatch>> {
+    let outputs = output_types(nodes);
+    let mut mismatches = Vec::new();
+    for (index, node) in nodes.iter().enumerate() {
+        let Ok(node_type) = node.id else {
+            continue;
+        };
+        let signature = node_signature(node_type);
+        let generic = generic_type(node, &outputs);
+        for (input, target) in node.inputs.iter().enumerate() {
+            let Some(Some(found)) = outputs.get(*target as usize).copied() else {
+                continue;
+            };
+            let expected = match (signature.inputs.get(input), generic) {
+                (Some(Slot::Fixed(expected)), _) => Expected::Type(expected),
+                (Some(Slot::Generic), Some(generic)) if found != generic => Expected::Type(generic),
+                (Some(Slot::Generic), _) if signature.number && !found.is_number() => {
+                    Expected::Number
+                }
+                _ => continue,
+            };
+            if expected != Expected::Type(found) {
+                mismatches.push(TypeMismatch {
+                    node: index,
+                    input,
+                    expected,
+                    found,
+                });
+            }
+        }
+    }
+    if mismatches.is_empty() {
+        Ok(outputs)
+    } else {
+        Err(mismatches)
+    }
+}
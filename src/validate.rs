@@ -0,0 +1,189 @@
+// SPDX-License-Identifier: BSD-3-Clause
+// Copyright 2025 UxuginPython
+use super::*;
+//How many inputs a node type takes
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Arity {
+    Exactly(usize),
+    AtLeast(usize),
+}
+impl Arity {
+    pub fn accepts(self, count: usize) -> bool {
+        match self {
+            Self::Exactly(expected) => count == expected,
+            Self::AtLeast(minimum) => count >= minimum,
+        }
+    }
+}
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Exactly(expected) => write!(f, "{}", expected),
+            Self::AtLeast(minimum) => write!(f, "at least {}", minimum),
+        }
+    }
+}
+impl NodeType {
+    pub fn arity(self) -> Arity {
+        match self {
+            Self::ConstantGetter | Self::NoneGetter => Arity::Exactly(0),
+            Self::Expirer
+            | Self::CommandPID
+            | Self::EWMAStream
+            | Self::MovingAverageStream
+            | Self::PIDControllerStream
+            | Self::PositionToState
+            | Self::VelocityToState
+            | Self::AccelerationToState
+            | Self::NoneToError
+            | Self::NoneToValue
+            | Self::FloatToQuantity
+            | Self::QuantityToFloat
+            | Self::DimensionAdder
+            | Self::DimensionRemover
+            | Self::NotStream
+            | Self::DerivativeStream
+            | Self::IntegralStream => Arity::Exactly(1),
+            Self::FreezeStream
+            | Self::IfStream
+            | Self::AndStream
+            | Self::OrStream
+            | Self::Sum2
+            | Self::DifferenceStream
+            | Self::Product2
+            | Self::QuotientStream
+            | Self::ExponentStream => Arity::Exactly(2),
+            Self::IfElseStream => Arity::Exactly(3),
+            Self::Latest | Self::SumStream | Self::ProductStream => Arity::AtLeast(1),
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+#[non_exhaustive]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DiagnosticKind {
+    //The node's ID is not a known node type, so its inputs cannot be checked.
+    UnknownNodeType { id: u16 },
+    InputCount { expected: Arity, found: usize },
+    DanglingInput { input: usize, target: u16 },
+    SelfReference { input: usize },
+    //A loop with no delaying node in it, listed in the order data flows around it
+    AlgebraicLoop { path: Vec<usize> },
+}
+impl DiagnosticKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::UnknownNodeType { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownNodeType { id } => write!(f, "unknown node type {}", id),
+            Self::InputCount { expected, found } => {
+                write!(f, "takes {} inputs but has {}", expected, found)
+            }
+            Self::DanglingInput { input, target } => {
+                write!(
+                    f,
+                    "input {} refers to node {}, which does not exist",
+                    input, target
+                )
+            }
+            Self::SelfReference { input } => write!(f, "input {} refers to the node itself", input),
+            Self::AlgebraicLoop { path } => {
+                write!(f, "is part of a loop with no delaying node:")?;
+                for node in path {
+                    write!(f, " {} ->", node)?;
+                }
+                write!(f, " {}", path[0])
+            }
+        }
+    }
+}
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub node: usize,
+    pub kind: DiagnosticKind,
+}
+impl Diagnostic {
+    pub fn new(node: usize, kind: DiagnosticKind) -> Self {
+        Self {
+            severity: kind.severity(),
+            node,
+            kind,
+        }
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: node {} {}", self.severity, self.node, self.kind)
+    }
+}
+//Checks the input count of each node, that every input refers to another node which exists, and
+//that there are no loops without a delaying node. Diagnostics for single nodes come in node order
+//and loops come after them.
+pub fn validate(nodes: &[Node]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (index, node) in nodes.iter().enumerate() {
+        match node.id {
+            Ok(node_type) => {
+                let expected = node_type.arity();
+                if !expected.accepts(node.inputs.len()) {
+                    diagnostics.push(Diagnostic::new(
+                        index,
+                        DiagnosticKind::InputCount {
+                            expected,
+                            found: node.inputs.len(),
+                        },
+                    ));
+                }
+            }
+            Err(id) => diagnostics.push(Diagnostic::new(
+                index,
+                DiagnosticKind::UnknownNodeType { id },
+            )),
+        }
+        for (input, target) in node.inputs.iter().enumerate() {
+            if *target as usize >= nodes.len() {
+                diagnostics.push(Diagnostic::new(
+                    index,
+                    DiagnosticKind::DanglingInput {
+                        input,
+                        target: *target,
+                    },
+                ));
+            } else if *target as usize == index {
+                diagnostics.push(Diagnostic::new(
+                    index,
+                    DiagnosticKind::SelfReference { input },
+                ));
+            }
+        }
+    }
+    //Loops of one node are already reported as self references.
+    for path in cycles::algebraic_loops(nodes) {
+        if path.len() > 1 {
+            diagnostics.push(Diagnostic::new(
+                path[0],
+                DiagnosticKind::AlgebraicLoop { path },
+            ));
+        }
+    }
+    diagnostics
+}
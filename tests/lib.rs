@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: BSD-3-Clause
 // Copyright 2025 UxuginPython
+//Test files are laid out with repr(packed) structs.
+#![allow(clippy::repr_packed_without_abi)]
 use rrtk_rsb::*;
 #[test]
 fn read_file_layout_broken() {
@@ -82,7 +84,7 @@ fn read_file_one_node() {
             tags::NODE_START,
             tags::NODE_ID,
             tags::SKIP_2,
-            4u16,
+            4u16.to_le(),
             tags::COORDINATES,
             tags::SKIP_16,
             0.0f64,
@@ -141,7 +143,7 @@ fn read_file_two_nodes() {
             tags::NODE_START,
             tags::NODE_ID,
             tags::SKIP_2,
-            5u16,
+            5u16.to_le(),
             tags::COORDINATES,
             tags::SKIP_16,
             0.0f64,
@@ -152,7 +154,7 @@ fn read_file_two_nodes() {
             tags::NODE_START,
             tags::NODE_ID,
             tags::SKIP_2,
-            4u16,
+            4u16.to_le(),
             tags::COORDINATES,
             tags::SKIP_16,
             0.0f64,
@@ -182,3 +184,1583 @@ fn build_and_read_file() {
     ];
     assert_eq!(nodes, read_file(&build_file(nodes.iter())).unwrap());
 }
+#[test]
+fn read_file_split_skips() {
+    #[allow(unused)]
+    #[repr(packed)]
+    struct TestFile(
+        [u8; 12],
+        [u8; 4],
+        i8,
+        i8,
+        i8,
+        i8,
+        u8,
+        i8,
+        u8,
+        i8,
+        i8,
+        [u8; 8],
+        i8,
+        [u8; 8],
+        i8,
+        i8,
+        i8,
+        i8,
+    );
+    let id = 4u16.to_le_bytes();
+    let file: [u8; 46] = unsafe {
+        core::mem::transmute(TestFile(
+            *b"rrtkstrmbldr",
+            [1u8, 0, 0, 0],
+            tags::NODE_SECTION_START,
+            tags::NODE_START,
+            tags::NODE_ID,
+            tags::SKIP_1,
+            id[0],
+            tags::SKIP_1,
+            id[1],
+            tags::COORDINATES,
+            tags::SKIP_8,
+            1.0f64.to_le_bytes(),
+            tags::SKIP_8,
+            2.0f64.to_le_bytes(),
+            tags::NODE_INPUT_LIST_START,
+            tags::NODE_INPUT_LIST_END,
+            tags::NODE_END,
+            tags::NODE_SECTION_END,
+        ))
+    };
+    assert_eq!(
+        read_file(&file.into()).unwrap(),
+        vec![Node::new(Ok(NodeType::CommandPID), 1.0, 2.0, vec![])]
+    );
+}
+#[test]
+fn tokenize_built_file() {
+    use token::*;
+    let nodes = [Node::new(Ok(NodeType::NotStream), 0.0, 0.0, vec![0])];
+    let file = build_file(nodes.iter());
+    let tokens = Tokenizer::new(&file[16..])
+        .map(|token| (token.kind, token.range.start + 16))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            (TokenKind::Tag(Ok(Tag::NodeSectionStart)), 16),
+            (TokenKind::Tag(Ok(Tag::NodeStart)), 17),
+            (TokenKind::Tag(Ok(Tag::NodeID)), 18),
+            (TokenKind::Skip(Tag::Skip2), 19),
+            (TokenKind::Numbers, 20),
+            (TokenKind::Tag(Ok(Tag::Coordinates)), 22),
+            (TokenKind::Skip(Tag::Skip16), 23),
+            (TokenKind::Numbers, 24),
+            (TokenKind::Tag(Ok(Tag::NodeInputListStart)), 40),
+            (TokenKind::Skip(Tag::SkipU8), 41),
+            (TokenKind::Numbers, 43),
+            (TokenKind::Tag(Ok(Tag::NodeInputListEnd)), 45),
+            (TokenKind::Tag(Ok(Tag::NodeEnd)), 46),
+            (TokenKind::Tag(Ok(Tag::NodeSectionEnd)), 47),
+        ]
+    );
+}
+#[test]
+fn read_file_error_location() {
+    let nodes = [
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![]),
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![]),
+        Node::new(Ok(NodeType::NotStream), 0.0, 0.0, vec![0]),
+    ];
+    let mut file = build_file(nodes.iter());
+    //17 bytes of header and NODE_SECTION_START, then 26 bytes for the first node
+    assert_eq!(file[43], tags::NODE_START as u8);
+    assert_eq!(file[48], tags::COORDINATES as u8);
+    assert_eq!(file[49], tags::SKIP_16 as u8);
+    file[49] = tags::SKIP_8 as u8;
+    assert_eq!(
+        read_file(&file),
+        Err(error::ParseFile::ParseNode {
+            node: 1,
+            offset: 43,
+            error: error::parse_file::parse_node::ParseCoordinates::IncorrectLength { offset: 48 }
+                .into(),
+        })
+    );
+}
+#[test]
+fn read_file_multiple_node_sections_location() {
+    let mut file = build_file([Node::new(Ok(NodeType::NoneGetter), 0.0, 0.0, vec![])].iter());
+    let second_section = file.len();
+    file.extend_from_within(16..);
+    assert_eq!(
+        read_file(&file),
+        Err(error::ParseFile::MultipleNodeSections {
+            offset: second_section
+        })
+    );
+}
+#[test]
+fn parse_error_display_and_source() {
+    use core::error::Error;
+    let error = error::ParseFile::ParseNode {
+        node: 1,
+        offset: 43,
+        error: error::parse_file::parse_node::ParseInputs::LayoutBroken { offset: 70 }.into(),
+    };
+    let mut messages = vec![error.to_string()];
+    let mut source = error.source();
+    while let Some(some_source) = source {
+        messages.push(some_source.to_string());
+        source = some_source.source();
+    }
+    assert_eq!(
+        messages,
+        vec![
+            "failed to parse node 1 at byte 43",
+            "failed to parse input list",
+            "input list at byte 70 does not contain a whole number of inputs",
+        ]
+    );
+    let boxed: Box<dyn Error> = Box::new(error::ParseFile::Version);
+    assert_eq!(
+        boxed.to_string(),
+        "file specification version is not supported"
+    );
+}
+#[test]
+fn read_file_truncated() {
+    let nodes = [
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![]),
+        Node::new(Ok(NodeType::NotStream), 0.0, 0.0, vec![0]),
+    ];
+    let file = build_file(nodes.iter());
+    //Node 1 starts at byte 43. Its input list starts at byte 66 and ends at byte 71.
+    assert_eq!(file[66], tags::NODE_INPUT_LIST_START as u8);
+    assert_eq!(file[71], tags::NODE_INPUT_LIST_END as u8);
+    for (length, error) in [
+        (17, error::ParseFile::UnterminatedNodeSection { offset: 16 }),
+        (43, error::ParseFile::UnterminatedNodeSection { offset: 16 }),
+        (
+            44,
+            error::ParseFile::UnterminatedNode {
+                node: 1,
+                offset: 43,
+            },
+        ),
+        (50, error::ParseFile::TruncatedSkip { offset: 49 }),
+        (69, error::ParseFile::TruncatedSkip { offset: 67 }),
+        (
+            71,
+            error::ParseFile::UnterminatedInputList {
+                node: 1,
+                offset: 66,
+            },
+        ),
+        (
+            72,
+            error::ParseFile::UnterminatedNode {
+                node: 1,
+                offset: 43,
+            },
+        ),
+        (73, error::ParseFile::UnterminatedNodeSection { offset: 16 }),
+    ] {
+        assert_eq!(
+            read_file(&file[..length].to_vec()),
+            Err(error),
+            "{}",
+            length
+        );
+    }
+    assert_eq!(read_file(&file).unwrap(), nodes);
+}
+#[test]
+fn read_file_unterminated_input_list() {
+    let mut file = build_file([Node::new(Ok(NodeType::NoneGetter), 0.0, 0.0, vec![])].iter());
+    //Replace NODE_INPUT_LIST_END with a tag that does not mean anything yet.
+    let end = file.len() - 3;
+    assert_eq!(file[end], tags::NODE_INPUT_LIST_END as u8);
+    file[end] = 100;
+    assert_eq!(
+        read_file(&file),
+        Err(error::ParseFile::UnterminatedInputList {
+            node: 0,
+            offset: end - 1
+        })
+    );
+}
+#[test]
+fn build_file_many_inputs() {
+    let mut nodes = vec![Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![]); 40000];
+    for input_count in [128, 129, 300, 32768, 32769, 40000] {
+        nodes.push(Node::new(
+            Ok(NodeType::SumStream),
+            0.0,
+            0.0,
+            (0..input_count).map(|input| input as u16).collect(),
+        ));
+    }
+    assert_eq!(read_file(&build_file(nodes.iter())).unwrap(), nodes);
+    assert_eq!(
+        read_file(&try_build_file(nodes.iter()).unwrap()).unwrap(),
+        nodes
+    );
+}
+#[test]
+fn try_build_file_input_out_of_range() {
+    let nodes = [
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![]),
+        Node::new(Ok(NodeType::Sum2), 0.0, 0.0, vec![0, 2]),
+    ];
+    assert_eq!(
+        try_build_file(nodes.iter()),
+        Err(error::BuildFile::InputOutOfRange {
+            node: 1,
+            input: 1,
+            target: 2
+        })
+    );
+}
+#[test]
+fn try_build_file_too_many_nodes() {
+    let nodes = vec![Node::new(Ok(NodeType::NoneGetter), 0.0, 0.0, vec![]); 65537];
+    assert_eq!(
+        try_build_file(nodes.iter()),
+        Err(error::BuildFile::TooManyNodes { count: 65537 })
+    );
+    assert!(try_build_file(nodes[..65536].iter()).is_ok());
+}
+#[test]
+fn read_file_little_endian() {
+    #[rustfmt::skip]
+    let file = vec![
+        b'r', b'r', b't', b'k', b's', b't', b'r', b'm', b'b', b'l', b'd', b'r', 1, 0, 0, 0,
+        0x01, //NODE_SECTION_START
+        0x02, //NODE_START
+        0x00, 0x81, 0x1b, 0x00, //NODE_ID, SKIP_2, 27
+        0x03, 0x84, //COORDINATES, SKIP_16
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf8, 0x3f, //1.5
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0xc0, //-2.5
+        0x04, 0x85, 0x03, //NODE_INPUT_LIST_START, SKIP_U8, 4 bytes
+        0x01, 0x02, 0x03, 0x04, //513, 1027
+        0xfc, //NODE_INPUT_LIST_END
+        0xfe, //NODE_END
+        0xff, //NODE_SECTION_END
+    ];
+    assert_eq!(
+        read_file(&file).unwrap(),
+        vec![Node::new(
+            Ok(NodeType::Product2),
+            1.5,
+            -2.5,
+            vec![513, 1027]
+        )]
+    );
+    assert_eq!(build_file(read_file(&file).unwrap().iter()), file);
+}
+#[test]
+fn version_ordering() {
+    assert!(Version::new(0, 9, 0, 0) < Version::new(1, 0, 0, 0));
+    assert!(Version::new(1, 0, 0, 1) < Version::new(1, 0, 1, 0));
+    assert!(Version::new(MAJOR, MINOR + 1, 0, 0) > Version::CURRENT);
+    assert!(Version::CURRENT.is_compatible_with(Version::new(MAJOR, 200, 3, 0)));
+    assert!(!Version::CURRENT.is_compatible_with(Version::new(MAJOR + 1, 0, 0, 0)));
+}
+#[test]
+fn read_file_version_policy() {
+    let mut file = build_file([Node::new(Ok(NodeType::NoneGetter), 0.0, 0.0, vec![])].iter());
+    let nodes = read_file(&file).unwrap();
+    file[12..16].copy_from_slice(&[MAJOR, 0, 0, 0]);
+    assert_eq!(read_file(&file).unwrap(), nodes);
+    file[12..16].copy_from_slice(&[MAJOR - 1, 200, 0, 0]);
+    assert_eq!(read_file(&file), Err(error::ParseFile::Version));
+    assert_eq!(
+        read_file_with_policy(&file, VersionPolicy::Compatible),
+        Err(error::ParseFile::Version)
+    );
+    file[12..16].copy_from_slice(&[MAJOR, MINOR + 1, 0, 0]);
+    assert_eq!(read_file(&file), Err(error::ParseFile::Version));
+    assert_eq!(
+        read_file_with_policy(&file, VersionPolicy::Compatible).unwrap(),
+        nodes
+    );
+    file[12..16].copy_from_slice(&[MAJOR + 1, 0, 0, 0]);
+    assert_eq!(
+        read_file_with_policy(&file, VersionPolicy::Compatible),
+        Err(error::ParseFile::Version)
+    );
+}
+#[test]
+fn try_build_file_for_older_version() {
+    let nodes = [Node::new(Ok(NodeType::NoneGetter), 0.0, 0.0, vec![])];
+    let file = try_build_file_for_version(nodes.iter(), Version::CURRENT).unwrap();
+    assert_eq!(file[..16], FILE_START);
+    assert_eq!(read_file(&file).unwrap(), nodes);
+    for version in [
+        Version::new(MAJOR, MINOR + 1, 0, 0),
+        Version::new(MAJOR - 1, 9, 0, 0),
+    ] {
+        assert_eq!(
+            try_build_file_for_version(nodes.iter(), version),
+            Err(error::BuildFile::UnsupportedVersion(version))
+        );
+    }
+    let file = try_build_file_for_version(nodes.iter(), Version::new(1, 0, 0, 0)).unwrap();
+    #[rustfmt::skip]
+    assert_eq!(
+        file,
+        vec![
+            b'r', b'r', b't', b'k', b's', b't', b'r', b'm', b'b', b'l', b'd', b'r', 1, 0, 0, 0,
+            0x01, //NODE_SECTION_START
+            0x02, //NODE_START
+            0x00, 0x81, 0x01, 0x00, //NODE_ID, SKIP_2, 1
+            0x03, 0x84, //COORDINATES, SKIP_16
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0x04, 0xfc, //NODE_INPUT_LIST_START, NODE_INPUT_LIST_END
+            0xfe, //NODE_END
+            0xff, //NODE_SECTION_END
+        ]
+    );
+    assert_eq!(read_file(&file).unwrap(), nodes);
+}
+#[test]
+fn read_file_parameters() {
+    #[rustfmt::skip]
+    let file = vec![
+        b'r', b'r', b't', b'k', b's', b't', b'r', b'm', b'b', b'l', b'd', b'r', 1, 1, 0, 0,
+        0x01, //NODE_SECTION_START
+        0x02, //NODE_START
+        0x00, 0x81, 0xe8, 0x03, //NODE_ID, SKIP_2, 1000
+        0x03, 0x84, //COORDINATES, SKIP_16
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0x04, 0xfc, //NODE_INPUT_LIST_START, NODE_INPUT_LIST_END
+        0x05, //PARAMETER_LIST_START
+        0x06, 0x83, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf8, 0x3f, //PARAMETER_FLOAT, SKIP_8, 1.5
+        0x07, 0x83, 0xfd, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, //PARAMETER_INTEGER, SKIP_8, -3
+        0x08, 0x80, 0x01, //PARAMETER_BOOL, SKIP_1, true
+        0x09, 0x83, 0x00, 0xca, 0x9a, 0x3b, 0x00, 0x00, 0x00, 0x00, //PARAMETER_TIME, SKIP_8, 1 s
+        0xfb, //PARAMETER_LIST_END
+        0xfe, //NODE_END
+        0xff, //NODE_SECTION_END
+    ];
+    let nodes = vec![Node::new(Err(1000), 0.0, 0.0, vec![]).with_parameters(vec![
+        Parameter::Float(1.5),
+        Parameter::Integer(-3),
+        Parameter::Bool(true),
+        Parameter::Time(1_000_000_000),
+    ])];
+    assert_eq!(read_file(&file).unwrap(), nodes);
+    assert_eq!(build_file(nodes.iter()), file);
+}
+#[test]
+fn build_and_read_file_parameters() {
+    let nodes = vec![
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![])
+            .with_parameters(vec![Parameter::Float(2.0)]),
+        Node::new(Ok(NodeType::NotStream), 0.0, 0.0, vec![0]).with_parameters(vec![]),
+        Node::new(Ok(NodeType::NotStream), 0.0, 0.0, vec![1]),
+    ];
+    assert_eq!(read_file(&build_file(nodes.iter())).unwrap(), nodes);
+    assert_eq!(
+        try_build_file_for_version(nodes.iter(), Version::new(1, 0, 0, 0)),
+        Err(error::BuildFile::Downconvert {
+            version: Version::new(1, 0, 0, 0),
+            unsupported: vec![
+                error::build_file::Unsupported {
+                    node: 0,
+                    feature: Feature::Parameters
+                },
+                error::build_file::Unsupported {
+                    node: 1,
+                    feature: Feature::Parameters
+                },
+            ],
+        })
+    );
+}
+#[test]
+fn read_file_parameter_errors() {
+    let nodes = [Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![])
+        .with_parameters(vec![Parameter::Bool(false)])];
+    let file = build_file(nodes.iter());
+    //The parameter list starts right after the 26 bytes of node 0 without parameters.
+    let start = 17 + 25;
+    assert_eq!(file[start], tags::PARAMETER_LIST_START as u8);
+    let mut bad_bool = file.clone();
+    bad_bool[start + 3] = 2;
+    let mut bad_length = file.clone();
+    bad_length[start + 2] = tags::SKIP_2 as u8;
+    bad_length.insert(start + 4, 0);
+    for (file, error) in [
+        (
+            bad_bool,
+            error::parse_file::parse_node::ParseParameters::InvalidBool { offset: start + 1 },
+        ),
+        (
+            bad_length,
+            error::parse_file::parse_node::ParseParameters::IncorrectLength { offset: start + 1 },
+        ),
+    ] {
+        assert_eq!(
+            read_file(&file),
+            Err(error::ParseFile::ParseNode {
+                node: 0,
+                offset: 17,
+                error: error.into()
+            })
+        );
+    }
+    assert_eq!(
+        read_file(&file[..start + 4].to_vec()),
+        Err(error::ParseFile::UnterminatedParameterList {
+            node: 0,
+            offset: start
+        })
+    );
+}
+#[test]
+fn build_and_read_file_value_types() {
+    let nodes = [
+        ValueType::F32,
+        ValueType::F64,
+        ValueType::Bool,
+        ValueType::I64,
+        ValueType::Quantity(Dimension::new(1, -2, 0)),
+    ]
+    .map(|value_type| {
+        Node::new(Ok(NodeType::Latest), 0.0, 0.0, vec![]).with_value_type(value_type)
+    });
+    let file = build_file(nodes.iter());
+    assert_eq!(read_file(&file).unwrap(), nodes);
+    assert_eq!(nodes[0].features(), vec![Feature::ValueType]);
+    assert!(try_build_file_for_version(nodes.iter(), Version::new(1, 0, 0, 0)).is_err());
+}
+#[test]
+fn read_file_value_type_errors() {
+    let nodes = [Node::new(Ok(NodeType::SumStream), 0.0, 0.0, vec![])
+        .with_value_type(ValueType::Quantity(Dimension::new(1, 0, 0)))];
+    let file = build_file(nodes.iter());
+    let start = 17 + 25;
+    assert_eq!(file[start], tags::VALUE_TYPE as u8);
+    //VALUE_TYPE, SKIP_U8, length, code, exponents
+    let mut unknown = file.clone();
+    unknown[start + 3] = 200;
+    let mut short = file.clone();
+    short[start + 2] = 1;
+    short.drain(start + 5..start + 7);
+    for (file, error) in [
+        (
+            unknown,
+            error::parse_file::parse_node::ParseValueType::UnknownType {
+                offset: start,
+                code: 200,
+            },
+        ),
+        (
+            short,
+            error::parse_file::parse_node::ParseValueType::IncorrectLength { offset: start },
+        ),
+    ] {
+        assert_eq!(
+            read_file(&file),
+            Err(error::ParseFile::ParseNode {
+                node: 0,
+                offset: 17,
+                error: error.into()
+            })
+        );
+    }
+}
+#[test]
+fn node_parameters_round_trip() {
+    for parameters in [
+        NodeParameters::ConstantGetter {
+            value: Parameter::Bool(true),
+        },
+        NodeParameters::Expirer {
+            max_time_to_old: 5_000_000,
+        },
+        NodeParameters::PIDControllerStream {
+            setpoint: 5.0,
+            kp: 1.0,
+            ki: 0.01,
+            kd: 0.1,
+        },
+        NodeParameters::FloatToQuantity {
+            dimension: Dimension::new(1, -1, 0),
+        },
+        NodeParameters::IfElseStream,
+    ] {
+        let node = Node::from_typed_parameters(parameters, 0.0, 0.0, vec![]);
+        assert_eq!(node.id, Ok(parameters.node_type()));
+        assert_eq!(node.typed_parameters(), Some(Ok(parameters)));
+    }
+}
+#[test]
+fn node_parameters_schema_errors() {
+    for (node_type, parameters, error) in [
+        (
+            NodeType::EWMAStream,
+            vec![],
+            error::ParameterSchema::Missing { index: 0 },
+        ),
+        (
+            NodeType::IfElseStream,
+            vec![Parameter::Float(1.0)],
+            error::ParameterSchema::Extra { index: 0 },
+        ),
+        (
+            NodeType::MovingAverageStream,
+            vec![Parameter::Float(1.0)],
+            error::ParameterSchema::WrongType { index: 0 },
+        ),
+        (
+            NodeType::DimensionAdder,
+            vec![
+                Parameter::Integer(1),
+                Parameter::Integer(1000),
+                Parameter::Integer(0),
+            ],
+            error::ParameterSchema::OutOfRange { index: 1 },
+        ),
+    ] {
+        assert_eq!(
+            NodeParameters::from_parameters(node_type, &parameters),
+            Err(error)
+        );
+    }
+}
+#[test]
+fn read_file_parameter_schema_error() {
+    let nodes = [Node::new(Ok(NodeType::EWMAStream), 0.0, 0.0, vec![])
+        .with_parameters(vec![Parameter::Time(1)])];
+    assert_eq!(
+        read_file(&build_file(nodes.iter())),
+        Err(error::ParseFile::ParseNode {
+            node: 0,
+            offset: 17,
+            error: error::parse_file::parse_node::ParseParameters::Schema {
+                offset: 17 + 25,
+                error: error::ParameterSchema::WrongType { index: 0 },
+            }
+            .into(),
+        })
+    );
+}
+#[test]
+fn typecheck_graph() {
+    let nodes = [
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![])
+            .with_parameters(vec![Parameter::Float(1.0)]),
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![])
+            .with_parameters(vec![Parameter::Bool(true)]),
+        Node::new(Ok(NodeType::FloatToQuantity), 0.0, 0.0, vec![0]),
+        Node::new(Ok(NodeType::Sum2), 0.0, 0.0, vec![2, 2]),
+        Node::new(Ok(NodeType::QuantityToFloat), 0.0, 0.0, vec![3]),
+        Node::new(Ok(NodeType::IfStream), 0.0, 0.0, vec![1, 4]),
+        Node::new(Ok(NodeType::NotStream), 0.0, 0.0, vec![1]),
+        Node::new(Ok(NodeType::Latest), 0.0, 0.0, vec![]).with_value_type(ValueType::F64),
+        Node::new(Err(1000), 0.0, 0.0, vec![0]),
+    ];
+    assert_eq!(
+        typecheck(&nodes),
+        Ok(vec![
+            Some(StreamType::F32),
+            Some(StreamType::Bool),
+            Some(StreamType::Quantity),
+            Some(StreamType::Quantity),
+            Some(StreamType::F32),
+            Some(StreamType::F32),
+            Some(StreamType::Bool),
+            Some(StreamType::F64),
+            None,
+        ])
+    );
+    let mut bad = nodes.to_vec();
+    bad[6].inputs = vec![0];
+    bad[3].inputs = vec![2, 7];
+    bad.push(Node::new(Ok(NodeType::SumStream), 0.0, 0.0, vec![1, 1]));
+    assert_eq!(
+        typecheck(&bad),
+        Err(vec![
+            TypeMismatch {
+                node: 3,
+                input: 1,
+                expected: Expected::Type(StreamType::Quantity),
+                found: StreamType::F64,
+            },
+            TypeMismatch {
+                node: 6,
+                input: 0,
+                expected: Expected::Type(StreamType::Bool),
+                found: StreamType::F32,
+            },
+            TypeMismatch {
+                node: 9,
+                input: 0,
+                expected: Expected::Number,
+                found: StreamType::Bool,
+            },
+            TypeMismatch {
+                node: 9,
+                input: 1,
+                expected: Expected::Number,
+                found: StreamType::Bool,
+            },
+        ])
+    );
+    assert_eq!(
+        typecheck(&bad).unwrap_err()[1].to_string(),
+        "input 0 of node 6 is f32 but should be bool"
+    );
+}
+#[test]
+fn check_dimensions_graph() {
+    let position = Dimension::new(1, 0, 0);
+    let nodes = [
+        Node::from_typed_parameters(
+            NodeParameters::FloatToQuantity {
+                dimension: position,
+            },
+            0.0,
+            0.0,
+            vec![],
+        ),
+        Node::new(Ok(NodeType::DerivativeStream), 0.0, 0.0, vec![0]),
+        Node::new(Ok(NodeType::Product2), 0.0, 0.0, vec![1, 0]),
+        Node::new(Ok(NodeType::QuotientStream), 0.0, 0.0, vec![2, 1]),
+        Node::new(Ok(NodeType::Sum2), 0.0, 0.0, vec![3, 0]),
+        Node::new(Ok(NodeType::VelocityToState), 0.0, 0.0, vec![1]),
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![])
+            .with_value_type(ValueType::Quantity(Dimension::new(0, 0, 1))),
+        Node::from_typed_parameters(
+            NodeParameters::DimensionRemover {
+                dimension: Dimension::new(0, 0, 1),
+            },
+            0.0,
+            0.0,
+            vec![6],
+        ),
+        Node::new(Ok(NodeType::IntegralStream), 0.0, 0.0, vec![7]),
+    ];
+    assert_eq!(
+        check_dimensions(&nodes),
+        Ok(vec![
+            Some(position),
+            Some(Dimension::new(1, -1, 0)),
+            Some(Dimension::new(2, -1, 0)),
+            Some(position),
+            Some(position),
+            None,
+            Some(Dimension::new(0, 0, 1)),
+            Some(Dimension::NONE),
+            Some(Dimension::new(0, 1, 0)),
+        ])
+    );
+    let mut bad = nodes.to_vec();
+    bad[4].inputs = vec![3, 1];
+    bad[5].inputs = vec![0];
+    assert_eq!(
+        check_dimensions(&bad),
+        Err(vec![
+            DimensionMismatch {
+                node: 4,
+                input: 1,
+                expected: position,
+                found: Dimension::new(1, -1, 0),
+            },
+            DimensionMismatch {
+                node: 5,
+                input: 0,
+                expected: Dimension::new(1, -1, 0),
+                found: position,
+            },
+        ])
+    );
+    assert_eq!(
+        check_dimensions(&bad).unwrap_err()[0].to_string(),
+        "input 1 of node 4 has dimension L^1 T^-1 M^0 but should have L^1 T^0 M^0"
+    );
+}
+#[test]
+fn validate_graph() {
+    let nodes = [
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![]),
+        Node::new(Ok(NodeType::Sum2), 0.0, 0.0, vec![0, 0, 0, 0, 0]),
+        Node::new(Ok(NodeType::NotStream), 0.0, 0.0, vec![]),
+        Node::new(Ok(NodeType::SumStream), 0.0, 0.0, vec![0, 900, 3]),
+        Node::new(Err(1000), 0.0, 0.0, vec![0]),
+        Node::new(Ok(NodeType::Latest), 0.0, 0.0, vec![0, 1, 3]),
+    ];
+    assert_eq!(
+        validate(&nodes),
+        vec![
+            Diagnostic::new(
+                1,
+                DiagnosticKind::InputCount {
+                    expected: Arity::Exactly(2),
+                    found: 5
+                }
+            ),
+            Diagnostic::new(
+                2,
+                DiagnosticKind::InputCount {
+                    expected: Arity::Exactly(1),
+                    found: 0
+                }
+            ),
+            Diagnostic::new(
+                3,
+                DiagnosticKind::DanglingInput {
+                    input: 1,
+                    target: 900
+                }
+            ),
+            Diagnostic::new(3, DiagnosticKind::SelfReference { input: 2 }),
+            Diagnostic::new(4, DiagnosticKind::UnknownNodeType { id: 1000 }),
+        ]
+    );
+    let diagnostics = validate(&nodes);
+    assert_eq!(diagnostics[4].severity, Severity::Warning);
+    assert_eq!(diagnostics[2].severity, Severity::Error);
+    assert_eq!(
+        diagnostics[2].to_string(),
+        "error: node 3 input 1 refers to node 900, which does not exist"
+    );
+    assert_eq!(
+        diagnostics[1].to_string(),
+        "error: node 2 takes 1 inputs but has 0"
+    );
+}
+#[test]
+fn algebraic_loop_detection() {
+    let nodes = [
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![]),
+        //0 and 3 go into a Sum2 which feeds back into itself through a Latest.
+        Node::new(Ok(NodeType::Sum2), 0.0, 0.0, vec![0, 2]),
+        Node::new(Ok(NodeType::Latest), 0.0, 0.0, vec![1]),
+        //4, 5 and 6 form a loop with nothing to delay it.
+        Node::new(Ok(NodeType::Sum2), 0.0, 0.0, vec![0, 6]),
+        Node::new(Ok(NodeType::NotStream), 0.0, 0.0, vec![3]),
+        Node::new(Ok(NodeType::QuantityToFloat), 0.0, 0.0, vec![4]),
+        Node::new(Ok(NodeType::IfStream), 0.0, 0.0, vec![4, 5]),
+    ];
+    assert_eq!(
+        strongly_connected_components(&nodes),
+        vec![vec![0], vec![1, 2], vec![3, 4, 5, 6]]
+    );
+    assert_eq!(algebraic_loops(&nodes), vec![vec![3, 4, 6]]);
+    let diagnostics = validate(&nodes);
+    assert_eq!(
+        diagnostics,
+        vec![Diagnostic::new(
+            3,
+            DiagnosticKind::AlgebraicLoop {
+                path: vec![3, 4, 6]
+            }
+        )]
+    );
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "error: node 3 is part of a loop with no delaying node: 3 -> 4 -> 6 -> 3"
+    );
+    let mut self_loop = nodes.to_vec();
+    self_loop[4].inputs = vec![4];
+    assert_eq!(algebraic_loops(&self_loop), vec![vec![4]]);
+    assert_eq!(
+        validate(&self_loop),
+        vec![Diagnostic::new(
+            4,
+            DiagnosticKind::SelfReference { input: 0 }
+        )]
+    );
+}
+#[test]
+fn evaluation_order_and_reorder() {
+    let nodes = vec![
+        Node::new(Ok(NodeType::Sum2), 0.0, 0.0, vec![3, 2]),
+        Node::new(Ok(NodeType::ConstantGetter), 1.0, 0.0, vec![]),
+        //This is fed back from node 0 through a delay.
+        Node::new(Ok(NodeType::Latest), 2.0, 0.0, vec![0]),
+        Node::new(Ok(NodeType::NotStream), 3.0, 0.0, vec![1]),
+    ];
+    assert_eq!(evaluation_order(&nodes), Ok(vec![1, 2, 3, 0]));
+    let sorted = sort_for_evaluation(&nodes).unwrap();
+    assert_eq!(
+        sorted.iter().map(|node| node.x).collect::<Vec<_>>(),
+        vec![1.0, 2.0, 3.0, 0.0]
+    );
+    assert_eq!(
+        sorted
+            .iter()
+            .map(|node| node.inputs.clone())
+            .collect::<Vec<_>>(),
+        vec![vec![], vec![3], vec![0], vec![2, 1]]
+    );
+    assert_eq!(evaluation_order(&sorted), Ok(vec![0, 1, 2, 3]));
+    assert_eq!(read_file(&build_file(sorted.iter())).unwrap(), sorted);
+    assert_eq!(reorder(&sorted, &[3, 0, 1, 2]), Ok(nodes.clone()));
+    assert_eq!(
+        reorder(&sorted, &[3, 0, 1]),
+        Err(ReorderError::WrongLength {
+            expected: 4,
+            found: 3
+        })
+    );
+    assert_eq!(
+        reorder(&sorted, &[3, 0, 1, 4]),
+        Err(ReorderError::IndexOutOfRange {
+            position: 3,
+            index: 4
+        })
+    );
+    assert_eq!(
+        reorder(&sorted, &[3, 0, 3, 2]),
+        Err(ReorderError::DuplicateIndex {
+            position: 2,
+            index: 3
+        })
+    );
+    let mut bad = nodes.clone();
+    bad[2].id = Ok(NodeType::NotStream);
+    let error = evaluation_order(&bad).unwrap_err();
+    assert_eq!(error, CycleError { path: vec![0, 2] });
+    assert_eq!(
+        error.to_string(),
+        "nodes form a loop with no delaying node: 0 -> 2 -> 0"
+    );
+}
+#[test]
+fn graph_editing() {
+    let nodes = vec![
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![]),
+        Node::new(Ok(NodeType::ConstantGetter), 1.0, 0.0, vec![]),
+        Node::new(Ok(NodeType::Sum2), 2.0, 0.0, vec![0, 1]),
+        Node::new(Ok(NodeType::NotStream), 3.0, 0.0, vec![2]),
+        Node::new(Ok(NodeType::Latest), 4.0, 0.0, vec![3, 2]),
+    ];
+    let file = build_file(nodes.iter());
+    let mut graph = Graph::from_nodes(read_file(&file).unwrap()).unwrap();
+    assert_eq!(graph.len(), 5);
+    assert_eq!(graph.to_nodes().unwrap(), nodes);
+    let handles = graph.handles().collect::<Vec<_>>();
+    //Node 3 can't be removed while node 4 still refers to it.
+    assert_eq!(graph.dependents(handles[3]), vec![(handles[4], 0)]);
+    assert_eq!(
+        graph.remove_node(handles[3]),
+        Err(error::Graph::NodeInUse {
+            node: handles[3],
+            dependent: handles[4],
+            input: 0
+        })
+    );
+    assert_eq!(graph.disconnect(handles[4], 0), Ok(handles[3]));
+    assert!(graph.remove_node(handles[3]).is_ok());
+    assert_eq!(graph.inputs(handles[4]), Ok(&[handles[2]][..]));
+    assert_eq!(
+        graph.remove_node(handles[3]),
+        Err(error::Graph::NodeNotFound(handles[3]))
+    );
+    let new = graph.add_node(Node::new(Ok(NodeType::NotStream), 5.0, 0.0, vec![]));
+    assert_ne!(new, handles[3]);
+    assert!(!graph.contains(handles[3]));
+    assert_eq!(
+        graph.connect(handles[3], new),
+        Err(error::Graph::NodeNotFound(handles[3]))
+    );
+    graph.connect(handles[1], new).unwrap();
+    graph.connect(new, handles[4]).unwrap();
+    assert_eq!(
+        graph.replace_input(handles[2], 1, handles[0]),
+        Ok(handles[1])
+    );
+    assert_eq!(graph.disconnect(handles[4], 0), Ok(handles[2]));
+    assert_eq!(
+        graph.disconnect(handles[4], 1),
+        Err(error::Graph::InputNotFound {
+            node: handles[4],
+            input: 1
+        })
+    );
+    graph.node_mut(new).unwrap().y = 1.0;
+    assert_eq!(graph.node(new).unwrap().y, 1.0);
+    assert_eq!(
+        graph.to_nodes().unwrap(),
+        vec![
+            Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![]),
+            Node::new(Ok(NodeType::ConstantGetter), 1.0, 0.0, vec![]),
+            Node::new(Ok(NodeType::Sum2), 2.0, 0.0, vec![0, 0]),
+            Node::new(Ok(NodeType::NotStream), 5.0, 1.0, vec![1]),
+            Node::new(Ok(NodeType::Latest), 4.0, 0.0, vec![3]),
+        ]
+    );
+    //Handles from one graph do not work with another, even where the slot exists.
+    let mut other = Graph::from_nodes(graph.to_nodes().unwrap()).unwrap();
+    assert_eq!(
+        other.remove_node(handles[0]),
+        Err(error::Graph::NodeNotFound(handles[0]))
+    );
+    assert_eq!(other.len(), graph.len());
+    let mut copy = graph.clone();
+    assert!(copy.remove_node(handles[4]).is_ok());
+    let looped = copy.add_node(Node::new(Ok(NodeType::Latest), 0.0, 0.0, vec![]));
+    copy.connect(looped, looped).unwrap();
+    assert!(copy.remove_node(looped).is_ok());
+    assert_eq!(
+        Graph::from_nodes(vec![Node::new(Ok(NodeType::NotStream), 0.0, 0.0, vec![1])]),
+        Err(error::Graph::InputOutOfRange {
+            node: 0,
+            input: 0,
+            target: 1
+        })
+    );
+}
+#[test]
+fn typed_builder() {
+    let nodes = Builder::build(|mut b| {
+        let a = b.constant(3.0f32);
+        let c = b.constant(4.0f32);
+        let sum = b.sum2(a, c);
+        let limit = b.constant(5.0f32);
+        let over = b.difference(sum, limit);
+        let position = b.float_to_quantity(over, Dimension::new(1, 0, 0));
+        let velocity = b.derivative(position);
+        let state = b.velocity_to_state(velocity);
+        let command = b.command_pid(state, 1.0, 0.0, 0.0);
+        let enabled = b.constant(true);
+        let disabled = b.not(enabled);
+        let held = b.freeze(command, disabled);
+        let latest = b.latest(&[held]);
+        b.set_position(latest, 10.0, 20.0);
+        assert_eq!(latest.index(), 12);
+        //Handles to marker types compare and print like any other.
+        assert_eq!(position, position);
+        let states = std::collections::HashSet::from([state, state]);
+        assert_eq!(states.len(), 1);
+        assert_eq!(format!("{:?}", command), "Handle { index: 8 }");
+        b.finish()
+    });
+    assert_eq!(
+        nodes[0],
+        Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![])
+            .with_parameters(vec![Parameter::Float(3.0)])
+            .with_value_type(ValueType::F32)
+    );
+    assert_eq!(
+        nodes[2],
+        Node::new(Ok(NodeType::Sum2), 0.0, 0.0, vec![0, 1])
+    );
+    assert_eq!(
+        nodes[12],
+        Node::new(Ok(NodeType::Latest), 10.0, 20.0, vec![11])
+    );
+    assert_eq!(validate(&nodes), vec![]);
+    assert!(typecheck(&nodes).is_ok());
+    assert!(check_dimensions(&nodes).is_ok());
+    assert_eq!(read_file(&build_file(nodes.iter())).unwrap(), nodes);
+}
+#[test]
+fn rsb_graph_macro() {
+    let nodes = rsb_graph! {
+        setpoint = ConstantGetter [Parameter::Float(5.0)] @ (0, 0);
+        error = DifferenceStream(setpoint, feedback) @ (10.0, 0);
+        pid = PIDControllerStream(error) [
+            Parameter::Float(0.0),
+            Parameter::Float(1.0),
+            Parameter::Float(0.1),
+            Parameter::Float(0.01),
+        ] @ (20, 0);
+        feedback = Latest(pid) @ (30, 5);
+        empty = NoneGetter @ (40, 0);
+    };
+    assert_eq!(
+        nodes,
+        vec![
+            Node::new(Ok(NodeType::ConstantGetter), 0.0, 0.0, vec![])
+                .with_parameters(vec![Parameter::Float(5.0)]),
+            Node::new(Ok(NodeType::DifferenceStream), 10.0, 0.0, vec![0, 3]),
+            Node::new(Ok(NodeType::PIDControllerStream), 20.0, 0.0, vec![1]).with_parameters(vec![
+                Parameter::Float(0.0),
+                Parameter::Float(1.0),
+                Parameter::Float(0.1),
+                Parameter::Float(0.01),
+            ]),
+            Node::new(Ok(NodeType::Latest), 30.0, 5.0, vec![2]),
+            Node::new(Ok(NodeType::NoneGetter), 40.0, 0.0, vec![]),
+        ]
+    );
+    assert_eq!(validate(&nodes), vec![]);
+}
+#[test]
+fn text_round_trip() {
+    let text = "\
+n0 = ConstantGetter [5.0] as f64 @ (0.0, 0.0);
+n1 = DifferenceStream(n0, n3) @ (10.0, -0.0);
+n2 = PIDControllerStream(n1) [0.0, 1.0, 1e-5, 1e20] @ (20.5, 0.0);
+n3 = Latest(n2) [] as Quantity(1, -1, 0) @ (30.0, 5.0);
+n4 = Expirer(n3) [1000000ns] @ (40.0, 0.0);
+n5 = #1000(n4, n4) [-3, true, false, NaN, -inf] as bool @ (50.0, 0.0);
+";
+    let nodes = from_text(text).unwrap();
+    assert_eq!(nodes[1].inputs, vec![0, 3]);
+    assert_eq!(nodes[5].id, Err(1000));
+    assert_eq!(nodes[4].parameters, Some(vec![Parameter::Time(1_000_000)]));
+    assert_eq!(to_text(&nodes), text);
+    let file = build_file(nodes.iter());
+    assert_eq!(to_text(&read_file(&file).unwrap()), text);
+    let handwritten = "
+        //Labels can be anything and refer forward.
+        output = NotStream(input) @ (1, 2);
+        input = ConstantGetter [true,] @ (0, 0);
+    ";
+    assert_eq!(
+        to_text(&from_text(handwritten).unwrap()),
+        "n0 = NotStream(n1) @ (1.0, 2.0);\nn1 = ConstantGetter [true] @ (0.0, 0.0);\n"
+    );
+    //A known ID written with a # is the named node type.
+    let nodes = from_text("n0 = #5 [0.5] @ (0.0, 0.0);").unwrap();
+    assert_eq!(
+        nodes,
+        from_text("n0 = EWMAStream [0.5] @ (0.0, 0.0);").unwrap()
+    );
+    assert_eq!(read_file(&build_file(nodes.iter())).unwrap(), nodes);
+    assert_eq!(to_text(&nodes), "n0 = EWMAStream [0.5] @ (0.0, 0.0);\n");
+    //Dangling inputs and NaNs with a sign or payload come back exactly as they were.
+    let nan = f64::from_bits(0x7ff8_0000_0000_0001);
+    let nodes = vec![
+        Node::new(Err(1000), -f64::NAN, nan, vec![5]).with_parameters(vec![
+            Parameter::Float(-f64::NAN),
+            Parameter::Float(f64::NAN),
+        ]),
+    ];
+    let text = to_text(&nodes);
+    assert_eq!(
+        text,
+        "n0 = #1000(#5) [NaN(0xfff8000000000000), NaN] \
+         @ (NaN(0xfff8000000000000), NaN(0x7ff8000000000001));\n"
+    );
+    let read = from_text(&text).unwrap();
+    assert_eq!(read[0].inputs, vec![5]);
+    assert_eq!(read[0].x.to_bits(), (-f64::NAN).to_bits());
+    assert_eq!(read[0].y.to_bits(), nan.to_bits());
+    assert_eq!(to_text(&read), text);
+    assert_eq!(
+        to_text(&read_file(&build_file(nodes.iter())).unwrap()),
+        text
+    );
+}
+#[test]
+fn text_errors() {
+    for (text, line, column, kind) in [
+        (
+            "a = Sum3 @ (0, 0);",
+            1,
+            5,
+            TextErrorKind::UnknownNodeType("Sum3".into()),
+        ),
+        (
+            "a = NotStream(b) @ (0, 0);",
+            1,
+            15,
+            TextErrorKind::UnknownLabel("b".into()),
+        ),
+        (
+            "a = NoneGetter @ (0, 0);\na = NoneGetter @ (0, 0);",
+            2,
+            1,
+            TextErrorKind::DuplicateLabel("a".into()),
+        ),
+        (
+            "a = NoneGetter @ (0, x);",
+            1,
+            22,
+            TextErrorKind::InvalidNumber("x".into()),
+        ),
+        (
+            "a = NoneGetter @ (0, 0)",
+            1,
+            24,
+            TextErrorKind::UnexpectedEnd,
+        ),
+        (
+            "a = NoneGetter @ (0, 0);\n  b = NoneGetter $",
+            2,
+            18,
+            TextErrorKind::UnexpectedCharacter('$'),
+        ),
+        (
+            "a = NoneGetter as f16 @ (0, 0);",
+            1,
+            19,
+            TextErrorKind::Expected("a value type"),
+        ),
+    ] {
+        assert_eq!(from_text(text), Err(TextError { line, column, kind }));
+    }
+    assert_eq!(
+        from_text("a = Sum3 @ (0, 0);").unwrap_err().to_string(),
+        "line 1, column 5: unknown node type Sum3"
+    );
+}
+#[test]
+fn simulate_arithmetic_and_logic() {
+    use sim::{Datum, Simulator, Value};
+    let nodes = rsb_graph! {
+        two = ConstantGetter [Parameter::Float(2.0)] @ (0, 0);
+        three = ConstantGetter [Parameter::Float(3.0)] @ (0, 0);
+        yes = ConstantGetter [Parameter::Bool(true)] @ (0, 0);
+        no = NotStream(yes) @ (0, 0);
+        sum = Sum2(two, three) @ (0, 0);
+        difference = DifferenceStream(sum, two) @ (0, 0);
+        product = Product2(difference, three) @ (0, 0);
+        quotient = QuotientStream(product, two) @ (0, 0);
+        power = ExponentStream(two, three) @ (0, 0);
+        total = SumStream(two, three, power, missing) @ (0, 0);
+        and = AndStream(yes, no) @ (0, 0);
+        or = OrStream(missing_bool, yes) @ (0, 0);
+        if_no = IfStream(no, two) @ (0, 0);
+        choice = IfElseStream(no, two, three) @ (0, 0);
+        missing = Sum2(two, missing_bool) @ (0, 0);
+        missing_bool = NoneGetter @ (0, 0);
+        one = ConstantGetter [Parameter::Integer(1)] @ (0, 0);
+        two_integers = Sum2(one, one) @ (0, 0);
+    };
+    for timestep in [0, -1] {
+        assert_eq!(
+            Simulator::new(nodes.clone(), timestep).unwrap_err(),
+            sim::SimulatorError::InvalidTimestep(timestep)
+        );
+    }
+    let mut simulator = Simulator::new(nodes, 10_000_000).unwrap();
+    assert_eq!(simulator.output(4), Ok(None));
+    simulator.step().unwrap();
+    assert_eq!(simulator.time(), 10_000_000);
+    assert_eq!(
+        simulator
+            .outputs()
+            .iter()
+            .map(|output| output.map(|datum| datum.map(|datum| datum.value)))
+            .collect::<Vec<_>>(),
+        vec![
+            Ok(Some(Value::Float(2.0))),
+            Ok(Some(Value::Float(3.0))),
+            Ok(Some(Value::Bool(true))),
+            Ok(Some(Value::Bool(false))),
+            Ok(Some(Value::Float(5.0))),
+            Ok(Some(Value::Float(3.0))),
+            Ok(Some(Value::Float(9.0))),
+            Ok(Some(Value::Float(4.5))),
+            Ok(Some(Value::Float(8.0))),
+            Ok(Some(Value::Float(13.0))),
+            Ok(Some(Value::Bool(false))),
+            Ok(Some(Value::Bool(true))),
+            Ok(None),
+            Ok(Some(Value::Float(3.0))),
+            Ok(None),
+            Ok(None),
+            Ok(Some(Value::Integer(1))),
+            Ok(Some(Value::Integer(2))),
+        ]
+    );
+    simulator.step().unwrap();
+    assert_eq!(simulator.time(), 20_000_000);
+    assert_eq!(
+        simulator.output(4),
+        Ok(Some(Datum::new(20_000_000, Value::Float(5.0))))
+    );
+    let nodes = rsb_graph! {
+        zero = ConstantGetter [Parameter::Integer(0)] @ (0, 0);
+        minus_one = ConstantGetter [Parameter::Integer(-1)] @ (0, 0);
+        minimum = ConstantGetter [Parameter::Integer(i64::MIN)] @ (0, 0);
+        by_zero = QuotientStream(minus_one, zero) @ (0, 0);
+        negative_power = ExponentStream(minus_one, minus_one) @ (0, 0);
+        overflow = QuotientStream(minimum, minus_one) @ (0, 0);
+    };
+    let mut simulator = Simulator::new(nodes, 10_000_000).unwrap();
+    simulator.step().unwrap();
+    assert_eq!(simulator.output(3), Err(sim::SimError::DivideByZero));
+    assert_eq!(simulator.output(4), Err(sim::SimError::NegativeExponent));
+    assert_eq!(simulator.output(5), Err(sim::SimError::Overflow));
+}
+#[test]
+fn simulate_stale_sensor() {
+    use sim::{Datum, SimError, Simulator, Value};
+    let nodes = rsb_graph! {
+        sensor = NoneGetter @ (0, 0);
+        fresh = Expirer(sensor) [Parameter::Time(50_000_000)] @ (0, 0);
+        or_zero = NoneToValue(fresh) [Parameter::Float(0.0)] @ (0, 0);
+        required = NoneToError(fresh) @ (0, 0);
+        doubled = Sum2(required, required) @ (0, 0);
+    };
+    let mut simulator = Simulator::new(nodes, 10_000_000).unwrap();
+    simulator.set_value(0, Value::Float(3.0)).unwrap();
+    let reading = Ok(Some(Datum::new(0, Value::Float(3.0))));
+    for _ in 0..5 {
+        simulator.step().unwrap();
+        assert_eq!(simulator.output(1), reading);
+        assert_eq!(simulator.output(2), reading);
+        assert_eq!(
+            simulator.output(4),
+            Ok(Some(Datum::new(0, Value::Float(6.0))))
+        );
+    }
+    //The reading is now more than 50ms old.
+    simulator.step().unwrap();
+    assert_eq!(simulator.time(), 60_000_000);
+    assert_eq!(simulator.output(0), reading);
+    assert_eq!(simulator.output(1), Ok(None));
+    assert_eq!(
+        simulator.output(2),
+        Ok(Some(Datum::new(60_000_000, Value::Float(0.0))))
+    );
+    assert_eq!(simulator.output(3), Err(SimError::FromNone));
+    assert_eq!(simulator.output(4), Err(SimError::FromNone));
+    simulator.set_value(0, Value::Float(4.0)).unwrap();
+    simulator.step().unwrap();
+    assert_eq!(
+        simulator.output(2),
+        Ok(Some(Datum::new(60_000_000, Value::Float(4.0))))
+    );
+    //Once released, the NoneGetter goes back to outputting None.
+    simulator.release(0).unwrap();
+    assert_eq!(
+        simulator.set_value(5, Value::Float(1.0)),
+        Err(sim::NodeNotFound { node: 5 })
+    );
+    assert_eq!(simulator.release(5), Err(sim::NodeNotFound { node: 5 }));
+    simulator.step().unwrap();
+    assert_eq!(simulator.output(0), Ok(None));
+    assert_eq!(simulator.output(3), Err(SimError::FromNone));
+}
+#[test]
+fn simulate_latest_and_freeze() {
+    use sim::{Datum, SimError, Simulator, Value};
+    let nodes = rsb_graph! {
+        primary = NoneGetter @ (0, 0);
+        backup = NoneGetter @ (0, 0);
+        newest = Latest(primary, backup) @ (0, 0);
+        hold = NoneGetter @ (0, 0);
+        held = FreezeStream(newest, hold) @ (0, 0);
+        //Counts up by one each step through the feedback.
+        one = ConstantGetter [Parameter::Integer(1)] @ (0, 0);
+        counter = Sum2(one, previous) @ (0, 0);
+        previous = NoneToValue(last) [Parameter::Integer(0)] @ (0, 0);
+        last = Latest(counter) @ (0, 0);
+    };
+    let mut simulator = Simulator::new(nodes, 1_000_000).unwrap();
+    simulator.set_output(0, Err(SimError::WrongType)).unwrap();
+    simulator.step().unwrap();
+    assert_eq!(simulator.output(2), Err(SimError::WrongType));
+    simulator.set_value(1, Value::Float(1.0)).unwrap();
+    simulator.step().unwrap();
+    assert_eq!(
+        simulator.output(2),
+        Ok(Some(Datum::new(1_000_000, Value::Float(1.0))))
+    );
+    simulator.set_value(0, Value::Float(2.0)).unwrap();
+    simulator.step().unwrap();
+    assert_eq!(
+        simulator.output(4),
+        Ok(Some(Datum::new(2_000_000, Value::Float(2.0))))
+    );
+    simulator.set_value(3, Value::Bool(true)).unwrap();
+    simulator.set_value(1, Value::Float(5.0)).unwrap();
+    simulator.step().unwrap();
+    assert_eq!(
+        simulator.output(2),
+        Ok(Some(Datum::new(3_000_000, Value::Float(5.0))))
+    );
+    assert_eq!(
+        simulator.output(4),
+        Ok(Some(Datum::new(2_000_000, Value::Float(2.0))))
+    );
+    assert_eq!(
+        simulator.output(6),
+        Ok(Some(Datum::new(4_000_000, Value::Integer(4))))
+    );
+}
+#[test]
+fn step_response_report() {
+    let samples = [
+        (0, 0.0),
+        (1, 0.05),
+        (2, 0.5),
+        (3, 0.95),
+        (4, 1.2),
+        (5, 1.01),
+        (6, 0.99),
+        (7, 1.0),
+    ];
+    assert_eq!(
+        sim::step_response(&samples, 0.0, 1.0).unwrap(),
+        sim::StepResponse {
+            rise_time: Some(1),
+            overshoot: 0.19999999999999996,
+            settling_time: Some(5),
+            steady_state_error: 0.0,
+        }
+    );
+    let response = sim::step_response(&samples[..5], 0.0, 1.0).unwrap();
+    assert_eq!(response.settling_time, None);
+    let response = sim::step_response(&samples[..2], 0.0, 1.0).unwrap();
+    assert_eq!(response.rise_time, None);
+    assert_eq!(sim::step_response(&[], 0.0, 1.0), None);
+    assert_eq!(sim::step_response(&samples, 1.0, 1.0), None);
+}
+#[test]
+fn simulate_closed_loop() {
+    use sim::{
+        Datum, DcMotor, FirstOrderLag, Integrator, Measurement, NodeNotFound, Simulator, Value,
+        step_response,
+    };
+    let nodes = rsb_graph! {
+        sensor = NoneGetter @ (0, 0);
+        control = PIDControllerStream(sensor) [
+            Parameter::Float(2.0),
+            Parameter::Float(2.0),
+            Parameter::Float(4.0),
+            Parameter::Float(0.0),
+        ] @ (0, 0);
+    };
+    let timestep = 10_000_000;
+    let run = |plant: &mut dyn FnMut(&mut Simulator) -> Result<(), NodeNotFound>| {
+        let mut simulator = Simulator::new(nodes.clone(), timestep).unwrap();
+        plant(&mut simulator).unwrap();
+        let mut samples = Vec::new();
+        for _ in 0..500 {
+            simulator.step().unwrap();
+            if let Ok(Some(Datum {
+                time,
+                value: Value::Float(value),
+            })) = simulator.output(0)
+            {
+                samples.push((time, value));
+            }
+        }
+        step_response(&samples, 0.0, 2.0).unwrap()
+    };
+    let lag = run(&mut |simulator| simulator.add_plant(FirstOrderLag::new(1.0, 0.5), 1, 0));
+    assert!(lag.steady_state_error.abs() < 0.01, "{:?}", lag);
+    assert!(lag.rise_time.unwrap() < 2_000_000_000, "{:?}", lag);
+    assert!(lag.settling_time.is_some(), "{:?}", lag);
+    let motor = run(&mut |simulator| {
+        simulator.add_plant(
+            DcMotor::new(1.0, 0.5, 0.5, 0.1, 0.01, Measurement::Velocity),
+            1,
+            0,
+        )
+    });
+    assert!(motor.steady_state_error.abs() < 0.05, "{:?}", motor);
+    //With the control input saturated at 1, this takes at least 2 seconds to get to 2.
+    let integrator =
+        run(&mut |simulator| simulator.add_plant(Integrator::new(1.0, 1.0, 0.001, 12345), 1, 0));
+    assert!(
+        integrator.rise_time.unwrap() >= 1_590_000_000,
+        "{:?}",
+        integrator
+    );
+    //The integral term winds up while the control input is saturated.
+    assert!(integrator.overshoot > 0.5, "{:?}", integrator);
+}
+#[test]
+fn simulate_command_pid() {
+    use sim::{
+        Command, Datum, DcMotor, Measurement, PositionDerivative, Simulator, State, Value,
+        step_response,
+    };
+    let nodes = rsb_graph! {
+        sensor = NoneGetter @ (0, 0);
+        position = FloatToQuantity(sensor) [
+            Parameter::Integer(1),
+            Parameter::Integer(0),
+            Parameter::Integer(0),
+        ] @ (0, 0);
+        state = PositionToState(position) @ (0, 0);
+        control = CommandPID(state) [
+            Parameter::Float(5.0),
+            Parameter::Float(1.0),
+            Parameter::Float(1.0),
+        ] @ (0, 0);
+    };
+    let mut simulator = Simulator::new(nodes, 10_000_000).unwrap();
+    let motor = DcMotor::new(1.0, 0.5, 0.5, 0.1, 0.01, Measurement::Position);
+    simulator.add_plant(motor, 3, 0).unwrap();
+    simulator.step().unwrap();
+    assert_eq!(simulator.output(3), Ok(None));
+    simulator
+        .set_command(3, Command::new(PositionDerivative::Position, 1.0))
+        .unwrap();
+    let mut samples = Vec::new();
+    for _ in 0..1000 {
+        simulator.step().unwrap();
+        if let Ok(Some(Datum {
+            time,
+            value: Value::State(State { position, .. }),
+        })) = simulator.output(2)
+        {
+            samples.push((time, position));
+        }
+    }
+    let response = step_response(&samples, 0.0, 1.0).unwrap();
+    assert!(response.steady_state_error.abs() < 0.01, "{:?}", response);
+    assert!(response.settling_time.is_some(), "{:?}", response);
+    //A velocity command is controlled by the integral of the PID output.
+    simulator
+        .set_command(3, Command::new(PositionDerivative::Velocity, 0.5))
+        .unwrap();
+    for _ in 0..1000 {
+        simulator.step().unwrap();
+    }
+    let Ok(Some(Datum {
+        value: Value::State(state),
+        ..
+    })) = simulator.output(2)
+    else {
+        panic!("{:?}", simulator.output(2));
+    };
+    assert!((state.velocity - 0.5).abs() < 0.01, "{:?}", state);
+}
+#[test]
+fn simulate_derivative_and_integral() {
+    use sim::{Datum, Simulator, Value};
+    let nodes = rsb_graph! {
+        time = NoneGetter @ (0, 0);
+        slope = DerivativeStream(time) @ (0, 0);
+        area = IntegralStream(time) @ (0, 0);
+    };
+    let mut simulator = Simulator::new(nodes, 500_000_000).unwrap();
+    simulator.set_value(0, Value::Float(0.0)).unwrap();
+    simulator.step().unwrap();
+    assert_eq!(simulator.output(1), Ok(None));
+    assert_eq!(
+        simulator.output(2),
+        Ok(Some(Datum::new(0, Value::Float(0.0))))
+    );
+    simulator.set_value(0, Value::Float(1.0)).unwrap();
+    simulator.step().unwrap();
+    simulator.step().unwrap();
+    let time = 500_000_000;
+    assert_eq!(
+        simulator.output(1),
+        Ok(Some(Datum::new(time, Value::Float(2.0))))
+    );
+    assert_eq!(
+        simulator.output(2),
+        Ok(Some(Datum::new(time, Value::Float(0.25))))
+    );
+}
+#[test]
+fn csv_round_trip() {
+    use sim::{CsvError, read_csv, write_csv};
+    let rows = vec![
+        vec![String::from("time"), String::from("a,b")],
+        vec![String::from("0"), String::from("say \"hi\"")],
+        vec![String::from("1"), String::new()],
+    ];
+    let text = write_csv(&rows);
+    assert_eq!(text, "time,\"a,b\"\n0,\"say \"\"hi\"\"\"\n1,\n");
+    assert_eq!(read_csv(&text), Ok(rows));
+    assert_eq!(
+        read_csv("time,n0\r\n\r\n0,1\r\n"),
+        Ok(vec![
+            vec![String::from("time"), String::from("n0")],
+            vec![String::from("0"), String::from("1")],
+        ])
+    );
+    assert_eq!(
+        read_csv("time\n\"0"),
+        Err(CsvError::UnterminatedQuote { line: 2 })
+    );
+    assert_eq!(
+        read_csv("ti\"me"),
+        Err(CsvError::MisplacedQuote { line: 1 })
+    );
+}
+#[test]
+fn simulate_replay() {
+    use sim::{NodeNotFound, SimError, SimulateError, SimulatorError, simulate};
+    let mut nodes = rsb_graph! {
+        sensor = NoneGetter @ (0, 0);
+        fresh = Expirer(sensor) [Parameter::Time(15_000_000)] @ (0, 0);
+        or_zero = NoneToValue(fresh) [Parameter::Float(0.0)] @ (0, 0);
+        half = ConstantGetter [Parameter::Float(0.5)] @ (0, 0);
+        sum = Sum2(sensor, half) @ (0, 0);
+        switch = NoneGetter @ (0, 0);
+        count = NoneGetter @ (0, 0);
+    };
+    nodes[5].value_type = Some(ValueType::Bool);
+    nodes[6].value_type = Some(ValueType::I64);
+    let file = build_file(nodes.iter());
+    //Whole numbers in a float column are still floats.
+    let inputs = "time,n0,n5,n6\n10000000,1.5,true,1\n20000000,,,\n40000000,2,false,2\n";
+    assert_eq!(
+        simulate(&file, inputs, 10_000_000, &[0, 1, 2, 4, 5, 6]).unwrap(),
+        "time,n0,n1,n2,n4,n5,n6\n\
+         10000000,1.5,1.5,1.5,2.0,true,1\n\
+         20000000,1.5,1.5,1.5,2.0,true,1\n\
+         30000000,1.5,,0.0,2.0,true,1\n\
+         40000000,2.0,2.0,2.0,2.5,false,2\n"
+    );
+    assert_eq!(
+        simulate(&file, inputs, 0, &[0]),
+        Err(SimulateError::Simulator(SimulatorError::InvalidTimestep(0)))
+    );
+    assert_eq!(
+        simulate(&file, inputs, 10_000_000, &[7]),
+        Err(SimulateError::NodeNotFound(NodeNotFound { node: 7 }))
+    );
+    assert_eq!(
+        simulate(&file, "time,n7\n", 10_000_000, &[0]),
+        Err(SimulateError::UnknownColumn { column: 1 })
+    );
+    assert_eq!(
+        simulate(&file, "time,n0,n5,n0\n", 10_000_000, &[0]),
+        Err(SimulateError::DuplicateColumn { column: 3 })
+    );
+    //The second step would be at 8e18 and the third would overflow.
+    assert_eq!(
+        simulate(
+            &file,
+            "time,n0\n9223372036854775807,1\n",
+            4_000_000_000_000_000_000,
+            &[0]
+        ),
+        Err(SimulateError::Step(SimError::TimeOverflow))
+    );
+    for cell in ["x", "1"] {
+        assert_eq!(
+            simulate(
+                &file,
+                &format!("time,n0,n5\n0,1,{}\n", cell),
+                10_000_000,
+                &[0]
+            ),
+            Err(SimulateError::InvalidValue { row: 1, column: 2 })
+        );
+    }
+    let mut simulator = sim::Simulator::new(nodes, 10_000_000).unwrap();
+    assert_eq!(
+        simulator.bind_trace(7, vec![]),
+        Err(NodeNotFound { node: 7 })
+    );
+    let mut simulator = sim::Simulator::new(vec![], i64::MAX).unwrap();
+    simulator.step().unwrap();
+    assert_eq!(simulator.step(), Err(SimError::TimeOverflow));
+    assert_eq!(simulator.time(), i64::MAX);
+}
+#[test]
+fn build_file_lowest_version() {
+    let node = Node::new(Ok(NodeType::NoneGetter), 0.0, 0.0, vec![]);
+    assert_eq!(node.required_version(), Version::new(1, 0, 0, 0));
+    assert_eq!(build_file([node.clone()].iter())[12..16], [1, 0, 0, 0]);
+    assert_eq!(
+        try_build_file([node.clone()].iter()).unwrap()[12..16],
+        [1, 0, 0, 0]
+    );
+    let node = node.with_parameters(vec![]);
+    assert_eq!(node.required_version(), Version::new(1, 1, 0, 0));
+    assert_eq!(build_file([node.clone()].iter())[12..16], [1, 1, 0, 0]);
+    assert_eq!(try_build_file([node].iter()).unwrap()[12..16], [1, 1, 0, 0]);
+}